@@ -474,4 +474,95 @@ mod tests {
         };
         assert_eq!(token.to_bytes(), vec![40]);
     }
+
+    #[test]
+    fn should_return_bytes_when_give_ldb() {
+        let token = AssemblerInstruction {
+            token: Some(Op { opcode: LDB }),
+            label: None,
+            directive: None,
+            operand1: Some(Register { reg_num: 1 }),
+            operand2: Some(Register { reg_num: 2 }),
+            operand3: Some(IntegerOperand { value: 300 }),
+        };
+        assert_eq!(token.to_bytes(), vec![41, 1, 2, 1, 44]);
+    }
+
+    #[test]
+    fn should_return_bytes_when_give_stw() {
+        let token = AssemblerInstruction {
+            token: Some(Op { opcode: STW }),
+            label: None,
+            directive: None,
+            operand1: Some(Register { reg_num: 1 }),
+            operand2: Some(Register { reg_num: 2 }),
+            operand3: Some(IntegerOperand { value: 4 }),
+        };
+        assert_eq!(token.to_bytes(), vec![45, 1, 2, 0, 4]);
+    }
+
+    #[test]
+    fn should_return_load32_bytes_when_give_load_above_u16() {
+        let token = AssemblerInstruction {
+            token: Some(Op { opcode: LOAD }),
+            label: None,
+            directive: None,
+            operand1: Some(Register { reg_num: 1 }),
+            operand2: Some(IntegerOperand { value: 70000 }),
+            operand3: None,
+        };
+        assert_eq!(token.to_bytes(), vec![48, 1, 0, 1, 17, 112]);
+    }
+
+    #[test]
+    fn should_return_load32_bytes_when_give_negative_load() {
+        let token = AssemblerInstruction {
+            token: Some(Op { opcode: LOAD }),
+            label: None,
+            directive: None,
+            operand1: Some(Register { reg_num: 1 }),
+            operand2: Some(IntegerOperand { value: -1 }),
+            operand3: None,
+        };
+        assert_eq!(token.to_bytes(), vec![48, 1, 255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn should_keep_load_bytes_when_give_load_max_u16() {
+        let token = AssemblerInstruction {
+            token: Some(Op { opcode: LOAD }),
+            label: None,
+            directive: None,
+            operand1: Some(Register { reg_num: 1 }),
+            operand2: Some(IntegerOperand { value: 65535 }),
+            operand3: None,
+        };
+        assert_eq!(token.to_bytes(), vec![1, 1, 255, 255]);
+    }
+
+    #[test]
+    fn should_return_bytes_when_give_addi_negative_immediate() {
+        let token = AssemblerInstruction {
+            token: Some(Op { opcode: ADDI }),
+            label: None,
+            directive: None,
+            operand1: Some(Register { reg_num: 1 }),
+            operand2: Some(IntegerOperand { value: -2 }),
+            operand3: Some(Register { reg_num: 2 }),
+        };
+        assert_eq!(token.to_bytes(), vec![81, 1, 255, 254, 2]);
+    }
+
+    #[test]
+    fn should_return_wide_address_bytes_when_give_jmpi() {
+        let token = AssemblerInstruction {
+            token: Some(Op { opcode: JMPI }),
+            label: None,
+            directive: None,
+            operand1: Some(IntegerOperand { value: 70000 }),
+            operand2: None,
+            operand3: None,
+        };
+        assert_eq!(token.to_bytes(), vec![84, 0, 1, 17, 112]);
+    }
 }
@@ -314,7 +314,7 @@ mod tests {
         assert_eq!(
             instructions[6],
             AssemblerInstruction {
-                token: Some(Op { opcode: JMP }),
+                token: Some(Op { opcode: JMPI }),
                 label: Some(LabelDeclaration {
                     name: "hello".to_string()
                 }),
@@ -362,7 +362,7 @@ mod tests {
         assert_eq!(
             instructions[10],
             AssemblerInstruction {
-                token: Some(Op { opcode: JE }),
+                token: Some(Op { opcode: JEI }),
                 label: None,
                 directive: None,
                 operand1: Some(LabelUsage {
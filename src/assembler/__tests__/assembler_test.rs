@@ -5,6 +5,9 @@
 #[cfg(test)]
 mod tests {
     use crate::assembler::assembler::Assembler;
+    use crate::assembler::assembler_error::AssemblerError::{
+        LabelNotFoundForTable, LabelOutOfRange, TmpRegisterInUse, UnknownLabel,
+    };
     use crate::assembler::assembler_instruction::AssemblerInstruction;
     use crate::assembler::assembler_section::AssemblerSection::{Code, Data};
     use crate::assembler::assembly_parser::AssemblyProgramParser;
@@ -13,20 +16,19 @@ mod tests {
         Directive, IntegerOperand, LabelDeclaration, LabelUsage, Op, Register,
     };
     use crate::vm::instruction::OpCode;
+    use crate::vm::vm::VM;
     use std::string::ToString;
 
     #[test]
     fn should_write_elf_header() {
         let assembler = Assembler::new();
-        let header = assembler.write_delf_header();
+        let header = assembler.write_delf_header(12, 0);
         assert_eq!(
             header,
             vec![
-                0x64, 0x65, 0x6C, 0x66, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+                0x64, 0x65, 0x6C, 0x66, 0, 0, 0, 1, 0, 0, 0, 64, 0, 0, 0, 0, 0, 0, 0, 64, 0, 0, 0,
+                12, 0, 0, 0, 76, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
             ]
         )
     }
@@ -60,11 +62,11 @@ mod tests {
         );
         assert_eq!(
             assembler.symbol_table.get_symbol("main"),
-            Some(&Symbol::new("main".to_string(), 0, SymbolType::Label))
+            Some(&Symbol::new("main".to_string(), 0, SymbolType::CodeLabel))
         );
         assert_eq!(
             assembler.symbol_table.get_symbol("hello"),
-            Some(&Symbol::new("hello".to_string(), 20, SymbolType::Label))
+            Some(&Symbol::new("hello".to_string(), 20, SymbolType::CodeLabel))
         );
         assert_eq!(
             assembler.symbol_table.get_symbol("hw"),
@@ -170,7 +172,7 @@ mod tests {
         );
         assert_eq!(
             assembler.symbol_table.get_symbol("main"),
-            Some(&Symbol::new("main".to_string(), 0, SymbolType::Label))
+            Some(&Symbol::new("main".to_string(), 0, SymbolType::CodeLabel))
         );
         assert_eq!(
             assembler.symbol_table.get_symbol("hw"),
@@ -193,12 +195,15 @@ mod tests {
         assert_eq!(
             result.unwrap(),
             vec![
-                0x64, 0x65, 0x6C, 0x66, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x01, 0x01, 0xF4, 0x01, 0x1F,
-                0x00, 0x00, 0x0E, 0x1F, 0x01, 0x1F, 0x00, 0x0C, 0x0E, 0x1F
+                0x64, 0x65, 0x6C, 0x66, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x50,
+                0x00, 0x00, 0x00, 0x22, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x01, 0xF4, 0x01, 0x1F,
+                0x00, 0x00, 0x0E, 0x1F, 0x01, 0x1F, 0x00, 0x0C, 0x0E, 0x1F, 0x68, 0x65, 0x6C, 0x6C,
+                0x6F, 0x2C, 0x57, 0x6F, 0x72, 0x6C, 0x64, 0x00, 0x68, 0x65, 0x6C, 0x6C, 0x6F, 0x2C,
+                0x20, 0x49, 0x20, 0x61, 0x6D, 0x20, 0x4E, 0x65, 0x72, 0x6F, 0x20, 0x59, 0x61, 0x6E,
+                0x67, 0x00,
             ]
         );
     }
@@ -237,15 +242,15 @@ mod tests {
         );
         assert_eq!(
             assembler.symbol_table.get_symbol("main"),
-            Some(&Symbol::new("main".to_string(), 0, SymbolType::Label))
+            Some(&Symbol::new("main".to_string(), 0, SymbolType::CodeLabel))
         );
         assert_eq!(
             assembler.symbol_table.get_symbol("hello"),
-            Some(&Symbol::new("hello".to_string(), 20, SymbolType::Label))
+            Some(&Symbol::new("hello".to_string(), 20, SymbolType::CodeLabel))
         );
         assert_eq!(
             assembler.symbol_table.get_symbol("foo"),
-            Some(&Symbol::new("foo".to_string(), 47, SymbolType::Label))
+            Some(&Symbol::new("foo".to_string(), 46, SymbolType::CodeLabel))
         );
         assert_eq!(
             assembler.symbol_table.get_symbol("hw"),
@@ -268,15 +273,18 @@ mod tests {
         assert_eq!(
             result.unwrap(),
             vec![
-                0x64, 0x65, 0x6C, 0x66, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x01, 0x01, 0xF4, 0x02, 0x00,
+                0x64, 0x65, 0x6C, 0x66, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x3C, 0x00, 0x00, 0x00, 0x7C,
+                0x00, 0x00, 0x00, 0x22, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x01, 0xF4, 0x02, 0x00,
                 0x01, 0x02, 0x03, 0x00, 0x01, 0x02, 0x04, 0x00, 0x01, 0x02, 0x05, 0x00, 0x01, 0x02,
-                0x06, 0x00, 0x07, 0x01, 0x08, 0x01, 0x09, 0x01, 0x02, 0x01, 0x1F, 0x00, 0x14, 0x0A,
-                0x1F, 0x01, 0x1F, 0x00, 0x00, 0x0E, 0x1F, 0x01, 0x1F, 0x00, 0x0C, 0x0E, 0x1F, 0x01,
-                0x00, 0x01, 0xF4, 0x02, 0x00, 0x01, 0x00, 0x01, 0x1F, 0x00, 0x2F, 0x06, 0x1F, 0x00
+                0x06, 0x00, 0x07, 0x01, 0x08, 0x01, 0x09, 0x01, 0x02, 0x55, 0x00, 0x00, 0x00, 0x14,
+                0x01, 0x1F, 0x00, 0x00, 0x0E, 0x1F, 0x01, 0x1F, 0x00, 0x0C, 0x0E, 0x1F, 0x01, 0x00,
+                0x01, 0xF4, 0x02, 0x00, 0x01, 0x00, 0x54, 0x00, 0x00, 0x00, 0x2E, 0x00, 0x68, 0x65,
+                0x6C, 0x6C, 0x6F, 0x2C, 0x57, 0x6F, 0x72, 0x6C, 0x64, 0x00, 0x68, 0x65, 0x6C, 0x6C,
+                0x6F, 0x2C, 0x20, 0x49, 0x20, 0x61, 0x6D, 0x20, 0x4E, 0x65, 0x72, 0x6F, 0x20, 0x59,
+                0x61, 0x6E, 0x67, 0x00,
             ]
         );
     }
@@ -340,14 +348,17 @@ mod tests {
         assert_eq!(
             result.unwrap(),
             vec![
-                0x64, 0x65, 0x6C, 0x66, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x00, 0x00, 0x00, 0x01, 0x01,
+                0x64, 0x65, 0x6C, 0x66, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x24, 0x00, 0x00, 0x00, 0x64,
+                0x00, 0x00, 0x00, 0x29, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x01,
                 0x00, 0x32, 0x01, 0x02, 0x00, 0x00, 0x09, 0x00, 0x01, 0x01, 0x1F, 0x00, 0x00, 0x0E,
-                0x1F, 0x0D, 0x01, 0x0C, 0x02, 0x01, 0x1F, 0x00, 0x0C, 0x0F, 0x1F, 0x01, 0x1F, 0x00,
-                0x0E, 0x0E, 0x1F,
+                0x1F, 0x0D, 0x01, 0x0C, 0x02, 0x56, 0x00, 0x00, 0x00, 0x0C, 0x01, 0x1F, 0x00, 0x0E,
+                0x0E, 0x1F, 0x48, 0x65, 0x6C, 0x6C, 0x6F, 0x2C, 0x20, 0x57, 0x6F, 0x72, 0x6C, 0x64,
+                0x2E, 0x00, 0x4F, 0x6B, 0x2C, 0x20, 0x35, 0x30, 0x20, 0x74, 0x69, 0x6D, 0x65, 0x73,
+                0x20, 0x70, 0x72, 0x69, 0x6E, 0x74, 0x20, 0x70, 0x61, 0x73, 0x73, 0x65, 0x64, 0x2E,
+                0x00,
             ]
         );
     }
@@ -369,14 +380,229 @@ mod tests {
         assert_eq!(
             result.unwrap(),
             vec![
-                0x64, 0x65, 0x6C, 0x66, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
-                0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x00, 0x00, 0x00, 0x01, 0x01,
-                0x00, 0x32, 0x01, 0x02, 0x00, 0x00, 0x09, 0x00, 0x01, 0x0D, 0x01, 0x0C, 0x02, 0x01,
-                0x1F, 0x00, 0x0C, 0x0F, 0x1F
+                0x64, 0x65, 0x6C, 0x66, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x18, 0x00, 0x00, 0x00, 0x58,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x01,
+                0x00, 0x32, 0x01, 0x02, 0x00, 0x00, 0x09, 0x00, 0x01, 0x0D, 0x01, 0x0C, 0x02, 0x56,
+                0x00, 0x00, 0x00, 0x0C,
             ]
         );
     }
+
+    #[test]
+    fn should_assemble_wide_immediate_loads() {
+        let mut assembler = Assembler::new();
+        let result = assembler.process(
+            ".code\n\
+             load $1 #70000\n\
+             load $2 #-3\n\
+             load $3 #3\n\
+             .data",
+        );
+        assert_eq!(
+            result.unwrap()[64..].to_vec(),
+            vec![48, 1, 0, 1, 17, 112, 48, 2, 255, 255, 255, 253, 1, 3, 0, 3]
+        );
+    }
+
+    #[test]
+    fn should_assemble_label_offset_above_u16() {
+        let mut assembler = Assembler::new();
+        let source = format!(
+            ".code\nprts @after\n.data\nbig: .asciiz \"{}\"\nafter: .asciiz \"end\"",
+            "a".repeat(70000)
+        );
+        let result = assembler.process(&source);
+        assert_eq!(
+            result.unwrap()[64..72].to_vec(),
+            vec![48, 31, 0, 1, 17, 113, 14, 31]
+        );
+    }
+
+    #[test]
+    fn should_resolve_forward_label_reference() {
+        let mut assembler = Assembler::new();
+        let result = assembler.process(
+            ".code\n\
+             load $0 #1\n\
+             eq $0 #1\n\
+             je @end\n\
+             load $1 #7\n\
+             end:\n\
+             hlt\n\
+             .data",
+        );
+        assert_eq!(
+            assembler.symbol_table.get_symbol("end"),
+            Some(&Symbol::new("end".to_string(), 17, SymbolType::CodeLabel))
+        );
+        assert_eq!(
+            result.unwrap()[64..].to_vec(),
+            vec![1, 0, 0, 1, 83, 0, 0, 1, 85, 0, 0, 0, 17, 1, 1, 0, 7, 0]
+        );
+    }
+
+    #[test]
+    fn should_not_use_tmp_register_for_immediate_forms() {
+        let mut assembler = Assembler::new();
+        let program = assembler
+            .process(
+                ".code\n\
+                 load $31 #99\n\
+                 load $0 #0\n\
+                 for: add $0 #2 $0\n\
+                 eq $0 #10\n\
+                 jne @for\n\
+                 sub $0 @for $1\n\
+                 hlt\n\
+                 .data",
+            )
+            .unwrap();
+        let mut vm = VM::new();
+        vm.load_program(program).unwrap();
+        vm.run().unwrap();
+        assert_eq!(vm.registers[0], 10);
+        assert_eq!(vm.registers[1], 2);
+        assert_eq!(vm.registers[31], 99);
+    }
+
+    #[test]
+    fn should_load_label_into_tmp_register_for_register_forms() {
+        let mut assembler = Assembler::new();
+        let program = assembler
+            .process(
+                ".code\n\
+                 jmpf @end\n\
+                 jmpb @end\n\
+                 jl @end\n\
+                 jg @end\n\
+                 prts @hello\n\
+                 end: hlt\n\
+                 .data\n\
+                 hello: .asciiz \"Hi\"",
+            )
+            .unwrap();
+        let mut expected = Vec::new();
+        for opcode in [OpCode::JMPF, OpCode::JMPB, OpCode::JL, OpCode::JG].iter() {
+            expected.extend_from_slice(&[OpCode::LOAD as u8, 31, 0, 30, *opcode as u8, 31]);
+        }
+        expected.extend_from_slice(&[OpCode::LOAD as u8, 31, 0, 0, OpCode::PRTS as u8, 31]);
+        expected.push(OpCode::HLT as u8);
+        assert_eq!(program[64..95].to_vec(), expected);
+    }
+
+    #[test]
+    fn should_report_tmp_register_used_with_label_expansion() {
+        let mut assembler = Assembler::new();
+        let result = assembler.process(
+            ".code\n\
+             load $31 #7\n\
+             prts @hello\n\
+             hlt $31\n\
+             .data\n\
+             hello: .asciiz \"Hi\"",
+        );
+        match result {
+            Err(errors) => {
+                assert_eq!(errors.len(), 2);
+                match &errors[0] {
+                    TmpRegisterInUse { instruction } => assert_eq!(*instruction, 1),
+                    e => panic!("unexpected error {:?}", e),
+                }
+            }
+            Ok(_) => panic!("$31 should not be usable next to prts @label"),
+        }
+    }
+
+    #[test]
+    fn should_report_unknown_label() {
+        let mut assembler = Assembler::new();
+        let result = assembler.process(".code\njmp @nowhere\n.data");
+        match result {
+            Err(errors) => match &errors[0] {
+                UnknownLabel { name } => assert_eq!(name, "nowhere"),
+                e => panic!("unexpected error {:?}", e),
+            },
+            Ok(_) => panic!("unknown label should not assemble"),
+        }
+    }
+
+    #[test]
+    fn should_fill_jump_table_with_code_offsets() {
+        let mut assembler = Assembler::new();
+        let result = assembler.process(
+            ".code\n\
+             calli @two\n\
+             hlt\n\
+             one: ret\n\
+             two: ret\n\
+             .data\n\
+             hw: .asciiz \"hi\"\n\
+             cases: .table @two @one",
+        );
+        assert_eq!(
+            result.unwrap()[64..72].to_vec(),
+            vec![89, 0, 0, 0, 7, 0, 40, 40]
+        );
+        assert_eq!(
+            assembler.symbol_table.get_symbol("cases"),
+            Some(&Symbol::new("cases".to_string(), 3, SymbolType::Label))
+        );
+        assert_eq!(
+            assembler.ro_section,
+            vec![104, 105, 0, 0, 0, 0, 7, 0, 0, 0, 6]
+        );
+    }
+
+    #[test]
+    fn should_report_label_outside_of_immediate_range() {
+        let data = format!(
+            ".data\npad: .asciiz \"{}\"\nfar: .asciiz \"x\"\n.code\n",
+            "a".repeat(40009)
+        );
+        let mut assembler = Assembler::new();
+        match assembler.process(&format!("{}add $0 @far $1\nhlt", data)) {
+            Err(errors) => match &errors[0] {
+                LabelOutOfRange { name, offset } => {
+                    assert_eq!(name, "far");
+                    assert_eq!(*offset, 40010);
+                }
+                e => panic!("unexpected error {:?}", e),
+            },
+            Ok(_) => panic!("a label past 32767 should not fit a signed immediate"),
+        }
+
+        let mut assembler = Assembler::new();
+        assert!(assembler
+            .process(&format!("{}ldrow $0 $1 @far\nload $2 @far\nhlt", data))
+            .is_ok());
+    }
+
+    #[test]
+    fn should_report_unknown_label_in_jump_table() {
+        let mut assembler = Assembler::new();
+        let result = assembler.process(".code\nhlt\n.data\ncases: .table @nowhere");
+        match result {
+            Err(errors) => match &errors[0] {
+                UnknownLabel { name } => assert_eq!(name, "nowhere"),
+                e => panic!("unexpected error {:?}", e),
+            },
+            Ok(_) => panic!("unknown label should not assemble"),
+        }
+    }
+
+    #[test]
+    fn should_report_table_without_label() {
+        let mut assembler = Assembler::new();
+        let result = assembler.process(".code\nmain: hlt\n.data\n.table @main");
+        match result {
+            Err(errors) => match &errors[0] {
+                LabelNotFoundForTable => {}
+                e => panic!("unexpected error {:?}", e),
+            },
+            Ok(_) => panic!("a table without a label should not assemble"),
+        }
+    }
 }
@@ -0,0 +1,138 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assembler::Assembler;
+    use crate::assembler::debug_info::DebugInfo;
+    use crate::assembler::disassembler::disassemble;
+    use crate::assembler::elf::DELFError::{BadDebugInfo, SectionOutOfBounds};
+    use crate::assembler::elf::DELFHeader;
+    use crate::vm::vm::VM;
+    use crate::vm::vm_io::SharedBuffer;
+
+    const FOR_EACH: &str = ".data\n\
+                            hello: .asciiz \"Hi\"\n\
+                            .code\n\
+                            main: load $1 #2\n\
+                            load $2 #0\n\
+                            for: prts @hello\n\
+                            dec $1\n\
+                            div $1 $2 $3\n\
+                            hlt";
+
+    fn assemble(source: &str) -> (Assembler, Vec<u8>) {
+        let mut assembler = Assembler::new();
+        assembler.enable_debug_info("for_each.asm");
+        let program = assembler.process(source).unwrap();
+        return (assembler, program);
+    }
+
+    #[test]
+    fn should_record_labels_and_lines() {
+        let (assembler, program) = assemble(FOR_EACH);
+        let debug_info = assembler.debug_info().unwrap();
+        assert_eq!(debug_info.source, "for_each.asm");
+        assert_eq!(
+            debug_info.code_labels,
+            vec![("main".to_string(), 0), ("for".to_string(), 8)]
+        );
+        assert_eq!(debug_info.data_labels, vec![("hello".to_string(), 0)]);
+        assert_eq!(
+            debug_info.lines,
+            vec![(0, 4), (4, 5), (8, 6), (14, 7), (16, 8), (20, 9)]
+        );
+
+        let header = DELFHeader::decode_from_bytes(&program).unwrap();
+        assert_eq!(header.debug_offset, header.ro_data_offset + 3);
+        let start = header.debug_offset as usize;
+        assert_eq!(
+            DebugInfo::decode_from_bytes(&program[start..]),
+            Ok(debug_info)
+        );
+    }
+
+    #[test]
+    fn should_not_write_debug_section_by_default() {
+        let mut assembler = Assembler::new();
+        let program = assembler.process(FOR_EACH).unwrap();
+        let header = DELFHeader::decode_from_bytes(&program).unwrap();
+        assert_eq!((header.debug_offset, header.debug_length), (0, 0));
+        assert_eq!(assembler.debug_info(), None);
+        assert_eq!(
+            program.len(),
+            (header.ro_data_offset + header.ro_data_length) as usize
+        );
+    }
+
+    #[test]
+    fn should_format_locations() {
+        let (assembler, _) = assemble(FOR_EACH);
+        let debug_info = assembler.debug_info().unwrap();
+        assert_eq!(debug_info.location(8), "for (for_each.asm:6)");
+        assert_eq!(debug_info.location(17), "for+9 (for_each.asm:8)");
+
+        let mut labels_only = DebugInfo::new("");
+        labels_only.code_labels = vec![("for".to_string(), 8)];
+        assert_eq!(labels_only.location(11), "for+3");
+        assert_eq!(labels_only.location(2), "pc=2");
+    }
+
+    #[test]
+    fn should_reject_bad_debug_section() {
+        let mut bytes = DebugInfo::new("a.asm").encode_to_bytes();
+        assert_eq!(
+            DebugInfo::decode_from_bytes(&bytes[..bytes.len() - 1]),
+            Err(BadDebugInfo { offset: 17 })
+        );
+        bytes.push(0);
+        assert_eq!(
+            DebugInfo::decode_from_bytes(&bytes),
+            Err(BadDebugInfo { offset: 21 })
+        );
+
+        let (_, mut program) = assemble(FOR_EACH);
+        program.truncate(program.len() - 1);
+        match VM::new().load_program(program) {
+            Err(SectionOutOfBounds { section, .. }) => assert_eq!(section, "debug"),
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+
+    #[test]
+    fn should_show_source_locations_in_vm() {
+        let (_, program) = assemble(FOR_EACH);
+        let output = SharedBuffer::new();
+        let mut vm = VM::new();
+        vm.set_output(Box::new(output.clone()));
+        vm.enable_profiler(false);
+        vm.load_program(program.clone()).unwrap();
+        assert_eq!(
+            vm.labels(),
+            &vec![("main".to_string(), 0), ("for".to_string(), 8)]
+        );
+
+        let error = vm.run().unwrap_err();
+        assert_eq!(vm.location(error.pc()), "for+8 (for_each.asm:8)");
+        assert_eq!(
+            vm.profile_report().unwrap().labels,
+            vec![("for".to_string(), 4), ("main".to_string(), 2)]
+        );
+        assert_eq!(vm.to_delf(), program);
+    }
+
+    #[test]
+    fn should_show_source_lines_in_disassembly() {
+        let (_, program) = assemble(FOR_EACH);
+        let listing = disassemble(&program).unwrap();
+        assert!(listing.starts_with(".data\n0000  hello: .asciiz \"Hi\"\n.code\nmain:\n"));
+        assert!(listing.contains(
+            "for:\n\
+             ; for_each.asm:6\n\
+             0008  load $31 @hello       ; \"Hi\"\n\
+             0012  prts $31\n\
+             ; for_each.asm:7\n"
+        ));
+    }
+}
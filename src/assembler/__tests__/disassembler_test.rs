@@ -0,0 +1,159 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assembler::Assembler;
+    use crate::assembler::disassembler::DataItem::{Bytes, StringConstant, Table};
+    use crate::assembler::disassembler::{disassemble, Disassembler};
+    use crate::assembler::elf::DELFError::BadMagic;
+    use crate::vm::instruction::OpCode::{ADDI, LOAD32, PRTS};
+    use crate::vm::instruction::OperandKind::{Immediate16, Immediate32, Register};
+
+    const PROGRAM: &str = ".data\n\
+                           hello: .asciiz \"Hello\\n\"\n\
+                           cases: .table @main @done\n\
+                           .code\n\
+                           main: prts @hello\n\
+                           addi $1 #-2 $2\n\
+                           call @done\n\
+                           hlt $2\n\
+                           done: ret";
+
+    fn disassembler() -> Disassembler {
+        let mut assembler = Assembler::new();
+        let program = assembler.process(PROGRAM).unwrap();
+        let mut disassembler = Disassembler::from_delf(&program).unwrap();
+        disassembler.set_labels(
+            assembler.symbol_table.code_labels(),
+            assembler.symbol_table.data_labels(),
+        );
+        return disassembler;
+    }
+
+    #[test]
+    fn should_decode_instructions() {
+        let instructions = disassembler().instructions();
+        assert_eq!(instructions.len(), 6);
+        assert_eq!(instructions[1].opcode, PRTS);
+        assert_eq!(instructions[1].operands, vec![(Register, 31)]);
+        assert_eq!(instructions[0].comment, Some("\"Hello\\n\"".to_string()));
+        assert_eq!(instructions[2].opcode, ADDI);
+        assert_eq!(
+            instructions[2].operands,
+            vec![(Register, 1), (Immediate16, 0xFFFE), (Register, 2)]
+        );
+        assert_eq!(instructions[3].operands, vec![(Immediate32, 18)]);
+    }
+
+    #[test]
+    fn should_decode_ro_data() {
+        assert_eq!(
+            disassembler().data(),
+            vec![
+                StringConstant {
+                    offset: 0,
+                    label: Some("hello".to_string()),
+                    text: "Hello\n".to_string()
+                },
+                Table {
+                    offset: 7,
+                    label: Some("cases".to_string()),
+                    entries: vec![0, 18]
+                }
+            ]
+        );
+        let unlabeled = Disassembler::new(vec![], vec![0x48, 0x69, 0, 0x01, 0x02]);
+        assert_eq!(
+            unlabeled.data(),
+            vec![
+                StringConstant {
+                    offset: 0,
+                    label: None,
+                    text: "Hi".to_string()
+                },
+                Bytes {
+                    offset: 3,
+                    label: None,
+                    bytes: vec![1, 2]
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn should_print_listing_with_labels() {
+        assert_eq!(
+            disassembler().to_string(),
+            ".data\n\
+             0000  hello: .asciiz \"Hello\\n\"\n\
+             0007  cases: .table @main @done\n\
+             .code\n\
+             main:\n\
+             0000  load $31 @hello       ; \"Hello\\n\"\n\
+             0004  prts $31\n\
+             0006  addi $1 #-2 $2\n\
+             0011  calli @done\n\
+             0016  hlt $2\n\
+             done:\n\
+             0018  ret"
+        );
+    }
+
+    #[test]
+    fn should_reassemble_ro_data_operands_as_labels() {
+        let data = ".data\nhello: .asciiz \"Hi\"\ncases: .table @main\n.code\n";
+        let mut assembler = Assembler::new();
+        let program = assembler
+            .process(&format!(
+                "{}main: ldrow $1 $2 @cases\nprts @hello\nhlt",
+                data
+            ))
+            .unwrap();
+        let mut disassembler = Disassembler::from_delf(&program).unwrap();
+        disassembler.set_labels(
+            assembler.symbol_table.code_labels(),
+            assembler.symbol_table.data_labels(),
+        );
+        let lines: Vec<String> = disassembler
+            .instructions()
+            .iter()
+            .map(|instruction| disassembler.format_instruction(instruction))
+            .collect();
+        assert_eq!(lines[0], "0000  ldrow $1 $2 @cases");
+        assert_eq!(lines[1], "0005  load $31 @hello       ; \"Hi\"");
+
+        let code: Vec<&str> = lines
+            .iter()
+            .map(|line| line[6..].split(';').next().unwrap().trim())
+            .collect();
+        let reassembled = Assembler::new()
+            .process(&format!("{}main: {}", data, code.join("\n")))
+            .unwrap();
+        assert_eq!(reassembled, program);
+    }
+
+    #[test]
+    fn should_disassemble_delf_without_symbols() {
+        let mut assembler = Assembler::new();
+        let program = assembler
+            .process(".data\n.code\nload $1 #100000\nhlt $1\njunk: hlt")
+            .unwrap();
+        let mut code = program.clone();
+        code[64 + 8] = 0xFE;
+        assert_eq!(
+            disassemble(&code).unwrap(),
+            ".data\n\
+             .code\n\
+             0000  load $1 #100000\n\
+             0006  hlt $1\n\
+             0008  ; not an instruction: FE"
+        );
+        assert_eq!(
+            Disassembler::from_delf(&program).unwrap().instructions()[0].opcode,
+            LOAD32
+        );
+        assert_eq!(disassemble(&[0; 64]), Err(BadMagic));
+    }
+}
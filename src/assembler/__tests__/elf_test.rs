@@ -0,0 +1,140 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assembler::Assembler;
+    use crate::assembler::elf::DELFError::{
+        BadMagic, EntryOutOfBounds, SectionOutOfBounds, TooShort, UnsupportedVersion,
+    };
+    use crate::assembler::elf::{DELFHeader, DELF_VERSION, ELF_HEADER_LENGTH};
+    use crate::vm::vm::VM;
+    use crate::vm::vm_io::SharedBuffer;
+
+    #[test]
+    fn should_encode_and_decode_header() {
+        let header = DELFHeader::for_sections(8, 20, 6);
+        assert_eq!(header.code_offset, 64);
+        assert_eq!(header.ro_data_offset, 84);
+
+        let bytes = header.encode_to_bytes();
+        assert_eq!(bytes.len(), ELF_HEADER_LENGTH);
+        assert_eq!(&bytes[4..8], &DELF_VERSION.to_be_bytes());
+        assert_eq!(DELFHeader::decode_from_bytes(&bytes), Ok(header));
+    }
+
+    #[test]
+    fn should_not_decode_without_header() {
+        assert_eq!(
+            DELFHeader::decode_from_bytes(&[0x64, 0x65, 0x6c, 0x66]),
+            Err(TooShort { length: 4 })
+        );
+        assert_eq!(DELFHeader::decode_from_bytes(&[0; 64]), Err(BadMagic));
+    }
+
+    #[test]
+    fn should_reject_unsupported_version() {
+        let mut bytes = DELFHeader::new().encode_to_bytes();
+        bytes[7] = 9;
+        assert_eq!(
+            DELFHeader::decode_from_bytes(&bytes),
+            Err(UnsupportedVersion { version: 9 })
+        );
+    }
+
+    #[test]
+    fn should_reject_sections_outside_of_file() {
+        let mut vm = VM::new();
+        let mut program = DELFHeader::for_sections(0, 8, 0).encode_to_bytes();
+        program.extend_from_slice(&[0, 0, 0, 0]);
+        let error = vm.load_program(program).unwrap_err();
+        assert_eq!(
+            error,
+            SectionOutOfBounds {
+                section: "code",
+                offset: 64,
+                length: 8,
+                file_length: 68
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Section code at 64 with 8 bytes is outside of the 68 bytes file"
+        );
+    }
+
+    #[test]
+    fn should_reject_entry_outside_of_code() {
+        let mut program = DELFHeader::for_sections(4, 4, 0).encode_to_bytes();
+        program.extend_from_slice(&[0, 0, 0, 0]);
+        assert_eq!(
+            VM::new().load_program(program),
+            Err(EntryOutOfBounds {
+                entry: 4,
+                code_length: 4
+            })
+        );
+    }
+
+    #[test]
+    fn should_reject_entry_inside_an_instruction() {
+        let mut program = DELFHeader::for_sections(2, 6, 0).encode_to_bytes();
+        /* LOAD $0 #1; HLT; HLT */
+        program.extend_from_slice(&[1, 0, 0, 1, 0, 0]);
+        assert_eq!(
+            VM::new().load_program(program),
+            Err(EntryOutOfBounds {
+                entry: 2,
+                code_length: 6
+            })
+        );
+    }
+
+    #[test]
+    fn should_load_sections_and_entry_from_program() {
+        let mut assembler = Assembler::new();
+        let program = assembler
+            .process(
+                ".data\n\
+                 hello: .asciiz \"Hello\"\n\
+                 .code\n\
+                 skip: load $1 #1\n\
+                 hlt\n\
+                 main: prts @hello\n\
+                 hlt",
+            )
+            .unwrap();
+        let header = DELFHeader::decode_from_bytes(&program).unwrap();
+        assert_eq!(header.entry, 5);
+        assert_eq!(header.code_length, 12);
+        assert_eq!(header.ro_data_length, 6);
+
+        let output = SharedBuffer::new();
+        let mut vm = VM::new();
+        vm.set_output(Box::new(output.clone()));
+        vm.set_exit_banner(false);
+        vm.load_program(program).unwrap();
+        assert_eq!(vm.ro_data, "Hello\0".as_bytes().to_vec());
+        vm.run().unwrap();
+        assert_eq!(output.text(), "Hello");
+        assert_eq!(vm.registers[1], 0);
+    }
+
+    #[test]
+    fn should_write_loaded_program_back_as_delf() {
+        let mut assembler = Assembler::new();
+        let program = assembler
+            .process(
+                ".data
+                 hello: .asciiz \"Hi\"
+                 .code
+                 main: prts @hello
+                 hlt",
+            )
+            .unwrap();
+        let mut vm = VM::new();
+        vm.load_program(program.clone()).unwrap();
+        assert_eq!(vm.to_delf(), program);
+    }
+}
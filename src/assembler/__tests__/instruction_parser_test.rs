@@ -7,7 +7,8 @@ mod tests {
     use crate::assembler::assembler_instruction::AssemblerInstruction;
     use crate::assembler::instructions_parser::InstructionParser;
     use crate::assembler::token::Token::{
-        Directive, IntegerOperand, IrString, LabelDeclaration, LabelUsage, Op, Register,
+        Directive, IntegerOperand, IrString, LabelDeclaration, LabelUsage, LabelUsageList, Op,
+        Register,
     };
     use crate::vm::instruction::OpCode;
     use crate::vm::instruction::OpCode::*;
@@ -609,7 +610,7 @@ mod tests {
 
     #[test]
     fn should_return_call_when_give_call() {
-        let mut token_parser = InstructionParser::new("call @foo");
+        let mut token_parser = InstructionParser::new("call $3");
         let token = token_parser.parse_instruction();
         assert_eq!(
             token.unwrap(),
@@ -617,9 +618,7 @@ mod tests {
                 token: Some(Op { opcode: CALL }),
                 label: None,
                 directive: None,
-                operand1: Some(LabelUsage {
-                    name: "foo".to_string()
-                }),
+                operand1: Some(Register { reg_num: 3 }),
                 operand2: None,
                 operand3: None,
             }
@@ -722,7 +721,7 @@ mod tests {
             label.unwrap(),
             AssemblerInstruction {
                 token: Some(Op {
-                    opcode: OpCode::JMP
+                    opcode: OpCode::JMPI
                 }),
                 label: Some(LabelDeclaration {
                     name: "hello".to_string()
@@ -745,7 +744,7 @@ mod tests {
             label.unwrap(),
             AssemblerInstruction {
                 token: Some(Op {
-                    opcode: OpCode::ADD
+                    opcode: OpCode::ADDI
                 }),
                 label: None,
                 directive: None,
@@ -901,4 +900,450 @@ mod tests {
             label.get_string_constant().unwrap()
         );
     }
+
+    #[test]
+    fn should_return_ldw_when_give_ldw() {
+        let mut token_parser = InstructionParser::new("ldw $1 $2 #8");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: LDW }),
+                label: None,
+                directive: None,
+                operand1: Some(Register { reg_num: 1 }),
+                operand2: Some(Register { reg_num: 2 }),
+                operand3: Some(IntegerOperand { value: 8 }),
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_stf64_when_give_stf64() {
+        let mut token_parser = InstructionParser::new("stf64 $3 $0 #16");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: STF64 }),
+                label: None,
+                directive: None,
+                operand1: Some(Register { reg_num: 3 }),
+                operand2: Some(Register { reg_num: 0 }),
+                operand3: Some(IntegerOperand { value: 16 }),
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_error_when_give_heap_offset_out_of_range() {
+        let mut token_parser = InstructionParser::new("stw $2 $1 #65540");
+        assert_eq!(
+            token_parser.parse_instruction(),
+            Err("An Unsigned Integer is expected(e.g. 1...65535)")
+        );
+        let mut token_parser = InstructionParser::new("ldb $2 $1 #-4");
+        assert_eq!(
+            token_parser.parse_instruction(),
+            Err("An Unsigned Integer is expected(e.g. 1...65535)")
+        );
+        let mut token_parser = InstructionParser::new("stw $2 $1 @hello");
+        assert_eq!(
+            token_parser.parse_instruction(),
+            Err("An Immediate number is expected(e.g. #1)")
+        );
+    }
+
+    #[test]
+    fn should_return_error_when_give_stb_without_offset() {
+        let mut token_parser = InstructionParser::new("stb $3 $0");
+        let token = token_parser.parse_instruction();
+        assert_eq!(token, Err("An Immediate number is expected(e.g. #1)"));
+    }
+
+    #[test]
+    fn should_return_aloc_when_give_aloc() {
+        let mut token_parser = InstructionParser::new("aloc $0 $1");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: ALOC }),
+                label: None,
+                directive: None,
+                operand1: Some(Register { reg_num: 0 }),
+                operand2: Some(Register { reg_num: 1 }),
+                operand3: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_free_when_give_free() {
+        let mut token_parser = InstructionParser::new("free $1");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: FREE }),
+                label: None,
+                directive: None,
+                operand1: Some(Register { reg_num: 1 }),
+                operand2: None,
+                operand3: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_error_when_give_loadf64_out_of_range() {
+        let mut token_parser = InstructionParser::new("loadf64 $0 #70000");
+        assert_eq!(
+            token_parser.parse_instruction(),
+            Err("An Unsigned Integer is expected(e.g. 1...65535)")
+        );
+        let mut token_parser = InstructionParser::new("loadf64 $0 #-1");
+        assert_eq!(
+            token_parser.parse_instruction(),
+            Err("An Unsigned Integer is expected(e.g. 1...65535)")
+        );
+    }
+
+    #[test]
+    fn should_return_loadf64_when_give_loadf64() {
+        let mut token_parser = InstructionParser::new("loadf64 $1 #300");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: LOADF64 }),
+                label: None,
+                directive: None,
+                operand1: Some(Register { reg_num: 1 }),
+                operand2: Some(IntegerOperand { value: 300 }),
+                operand3: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_itof_when_give_itof() {
+        let mut token_parser = InstructionParser::new("itof $1 $2");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: ITOF }),
+                label: None,
+                directive: None,
+                operand1: Some(Register { reg_num: 1 }),
+                operand2: Some(Register { reg_num: 2 }),
+                operand3: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_cmpf64_when_give_cmpf64() {
+        let mut token_parser = InstructionParser::new("cmpf64 $1 $2 $3");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: CMPF64 }),
+                label: None,
+                directive: None,
+                operand1: Some(Register { reg_num: 1 }),
+                operand2: Some(Register { reg_num: 2 }),
+                operand3: Some(Register { reg_num: 3 }),
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_sar_when_give_sar() {
+        let mut token_parser = InstructionParser::new("sar $1 $2 $3");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: SAR }),
+                label: None,
+                directive: None,
+                operand1: Some(Register { reg_num: 1 }),
+                operand2: Some(Register { reg_num: 2 }),
+                operand3: Some(Register { reg_num: 3 }),
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_loadrem_when_give_loadrem() {
+        let mut token_parser = InstructionParser::new("loadrem $4");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: LOADREM }),
+                label: None,
+                directive: None,
+                operand1: Some(Register { reg_num: 4 }),
+                operand2: None,
+                operand3: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_sltf64_when_give_sltf64() {
+        let mut token_parser = InstructionParser::new("sltf64 $1 $2 $3");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: SLTF64 }),
+                label: None,
+                directive: None,
+                operand1: Some(Register { reg_num: 1 }),
+                operand2: Some(Register { reg_num: 2 }),
+                operand3: Some(Register { reg_num: 3 }),
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_addi_when_give_add_with_immediate() {
+        let mut token_parser = InstructionParser::new("add $1 #-5 $2");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: ADDI }),
+                label: None,
+                directive: None,
+                operand1: Some(Register { reg_num: 1 }),
+                operand2: Some(IntegerOperand { value: -5 }),
+                operand3: Some(Register { reg_num: 2 }),
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_subi_when_give_sub_with_label() {
+        let mut token_parser = InstructionParser::new("sub $1 @size $2");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: SUBI }),
+                label: None,
+                directive: None,
+                operand1: Some(Register { reg_num: 1 }),
+                operand2: Some(LabelUsage {
+                    name: "size".to_string()
+                }),
+                operand3: Some(Register { reg_num: 2 }),
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_error_when_give_addi_immediate_above_i16() {
+        let mut token_parser = InstructionParser::new("addi $1 #40000 $2");
+        let token = token_parser.parse_instruction();
+        assert_eq!(token, Err("An Integer is expected(e.g. -32768...32767)"));
+    }
+
+    #[test]
+    fn should_return_cmpi_when_give_eq_with_immediate() {
+        let mut token_parser = InstructionParser::new("eq $3 #10");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: CMPI }),
+                label: None,
+                directive: None,
+                operand1: Some(Register { reg_num: 3 }),
+                operand2: Some(IntegerOperand { value: 10 }),
+                operand3: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_jmpi_when_give_jmp_with_immediate() {
+        let mut token_parser = InstructionParser::new("jmp #12");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: JMPI }),
+                label: None,
+                directive: None,
+                operand1: Some(IntegerOperand { value: 12 }),
+                operand2: None,
+                operand3: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_jnei_when_give_jne_with_label() {
+        let mut token_parser = InstructionParser::new("jne @for");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: JNEI }),
+                label: None,
+                directive: None,
+                operand1: Some(LabelUsage {
+                    name: "for".to_string()
+                }),
+                operand2: None,
+                operand3: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_loadlocal_when_give_loadlocal() {
+        let mut token_parser = InstructionParser::new("loadlocal $1 #-3");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: LOADLOCAL }),
+                label: None,
+                directive: None,
+                operand1: Some(Register { reg_num: 1 }),
+                operand2: Some(IntegerOperand { value: -3 }),
+                operand3: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_calli_when_give_call_with_label() {
+        let mut token_parser = InstructionParser::new("call @fact");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: CALLI }),
+                label: None,
+                directive: None,
+                operand1: Some(LabelUsage {
+                    name: "fact".to_string()
+                }),
+                operand2: None,
+                operand3: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_ldrow_when_give_ldrow_with_label() {
+        let mut token_parser = InstructionParser::new("ldrow $1 $2 @cases");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: LDROW }),
+                label: None,
+                directive: None,
+                operand1: Some(Register { reg_num: 1 }),
+                operand2: Some(Register { reg_num: 2 }),
+                operand3: Some(LabelUsage {
+                    name: "cases".to_string()
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_hltr_when_give_hlt_with_register() {
+        let mut token_parser = InstructionParser::new("hlt $2");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: HLTR }),
+                label: None,
+                directive: None,
+                operand1: Some(Register { reg_num: 2 }),
+                operand2: None,
+                operand3: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_syscall_when_give_syscall() {
+        let mut token_parser = InstructionParser::new("syscall #3");
+        let token = token_parser.parse_instruction();
+        assert_eq!(
+            token.unwrap(),
+            AssemblerInstruction {
+                token: Some(Op { opcode: SYSCALL }),
+                label: None,
+                directive: None,
+                operand1: Some(IntegerOperand { value: 3 }),
+                operand2: None,
+                operand3: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_error_when_give_syscall_number_too_large() {
+        let mut token_parser = InstructionParser::new("syscall #65536");
+        assert_eq!(
+            token_parser.parse_instruction(),
+            Err("An Unsigned Integer is expected(e.g. 0...65535)")
+        );
+    }
+
+    #[test]
+    fn should_return_error_when_give_syscall_label() {
+        let mut token_parser = InstructionParser::new("syscall @main");
+        assert_eq!(
+            token_parser.parse_instruction(),
+            Err("An Immediate number is expected(e.g. #1)")
+        );
+    }
+
+    #[test]
+    fn should_return_table_directive_when_parse_label_declaration() {
+        let mut instruction_parser = InstructionParser::new("cases: .table @zero @one");
+        let label = instruction_parser.parse_label_declaration();
+        assert_eq!(
+            label.unwrap(),
+            AssemblerInstruction {
+                token: None,
+                label: Some(LabelDeclaration {
+                    name: "cases".to_string()
+                }),
+                directive: Some(Directive {
+                    name: "table".to_string()
+                }),
+                operand1: Some(LabelUsageList {
+                    names: vec!["zero".to_string(), "one".to_string()]
+                }),
+                operand2: None,
+                operand3: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_return_error_when_give_table_without_labels() {
+        let mut instruction_parser = InstructionParser::new(".table #1");
+        let directive = instruction_parser.parse_directive();
+        assert_eq!(
+            directive,
+            Err("Expect labels for jump table(e.g. .table @foo @bar)")
+        );
+    }
 }
@@ -4,5 +4,8 @@
 pub mod assembler_instruction_test;
 pub mod assembler_parser_test;
 pub mod assembler_test;
+pub mod debug_info_test;
+pub mod disassembler_test;
+pub mod elf_test;
 pub mod instruction_parser_test;
 pub mod symbol_table_test;
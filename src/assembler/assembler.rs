@@ -4,15 +4,17 @@
 
 use crate::assembler::assembler_error::AssemblerError;
 use crate::assembler::assembler_error::AssemblerError::{
-    NoLabelNameFound, NoSectionDeclarationFound, SymbolAlreadyDeclared, UnknownDirectiveFound,
-    UnknownSectionFound,
+    LabelOutOfRange, NoLabelNameFound, NoSectionDeclarationFound, SymbolAlreadyDeclared,
+    TmpRegisterInUse, UnknownDirectiveFound, UnknownLabel, UnknownSectionFound,
 };
 use crate::assembler::assembler_instruction::AssemblerInstruction;
 use crate::assembler::assembler_phase::AssemblerPhase;
 use crate::assembler::assembler_phase::AssemblerPhase::FIRST;
 use crate::assembler::assembler_section::AssemblerSection;
 use crate::assembler::assembly_parser::AssemblyProgramParser;
-use crate::assembler::elf::{ELF_HEADER_LENGTH, ELF_HEADER_PREFIX};
+use crate::assembler::debug_info::DebugInfo;
+use crate::assembler::elf::DELFHeader;
+use crate::assembler::instructions_parser::InstructionParser;
 use crate::assembler::symbol_table::{Symbol, SymbolTable, SymbolType};
 use crate::assembler::token::Token;
 use crate::assembler::token::Token::{IntegerOperand, Op, Register};
@@ -25,10 +27,16 @@ pub struct Assembler {
     pub ro_section: Vec<u8>,
     byte_code: Vec<u8>,
     ro_offset: u32,
+    /* ro_section offset of each .table and the labels whose offsets fill it */
+    jump_tables: Vec<(usize, Vec<String>)>,
     sections: Vec<AssemblerSection>,
     pub(crate) current_section: Option<AssemblerSection>,
     current_instruction: u32,
     errors: Vec<AssemblerError>,
+    /* source file name when a debug section is written */
+    debug_source: Option<String>,
+    /* (pc, source line) of each instruction, instructions come one per line */
+    debug_lines: Vec<(usize, u32)>,
 }
 
 impl Assembler {
@@ -39,23 +47,54 @@ impl Assembler {
             ro_section: Vec::new(),
             byte_code: Vec::new(),
             ro_offset: 0,
+            jump_tables: Vec::new(),
             sections: Vec::new(),
             current_section: None,
             current_instruction: 0,
             errors: Vec::new(),
+            debug_source: None,
+            debug_lines: Vec::new(),
         }
     }
 
-    pub(crate) fn write_delf_header(&self) -> Vec<u8> {
-        let mut header: Vec<u8> = Vec::<u8>::new();
-        for byte in ELF_HEADER_PREFIX.into_iter() {
-            header.push(byte.clone());
-        }
-        while header.len() < ELF_HEADER_LENGTH {
-            header.push(0xFF as u8);
+    /* also write a debug section, `source` names the file in source locations */
+    pub fn enable_debug_info(&mut self, source: &str) {
+        self.debug_source = Some(source.to_string());
+    }
+
+    pub(crate) fn write_delf_header(&self, code_length: usize, debug_length: usize) -> Vec<u8> {
+        let header = DELFHeader::for_sections(
+            self.entry(),
+            code_length as u32,
+            self.ro_section.len() as u32,
+        );
+        if debug_length == 0 {
+            return header.encode_to_bytes();
         }
+        return header
+            .with_debug_section(debug_length as u32)
+            .encode_to_bytes();
+    }
+
+    /* symbol table and line numbers of the assembled program, when enabled */
+    pub fn debug_info(&self) -> Option<DebugInfo> {
+        let mut debug_info = DebugInfo::new(self.debug_source.as_ref()?);
+        debug_info.code_labels = self.symbol_table.code_labels();
+        debug_info.data_labels = self.symbol_table.data_labels();
+        debug_info.lines = self.debug_lines.clone();
+        return Some(debug_info);
+    }
 
-        return header;
+    /* programs start at the `main` label when there is one */
+    fn entry(&self) -> u32 {
+        return self
+            .symbol_table
+            .symbols
+            .iter()
+            .find(|symbol| {
+                symbol.name() == "main" && *symbol.symbol_type() == SymbolType::CodeLabel
+            })
+            .map_or(0, |symbol| symbol.offset());
     }
 
     pub fn process(&mut self, assembly: &str) -> Result<Vec<u8>, Vec<AssemblerError>> {
@@ -77,7 +116,6 @@ impl Assembler {
         &mut self,
         instructions: &Vec<AssemblerInstruction>,
     ) -> Result<Vec<u8>, Vec<AssemblerError>> {
-        let mut assembled_program: Vec<u8> = self.write_delf_header();
         self.process_first_phase(&instructions);
 
         if !self.errors.is_empty() {
@@ -90,9 +128,22 @@ impl Assembler {
             return Err(self.errors.clone());
         }
 
+        self.process_layout_phase(&instructions);
+        self.fill_jump_tables();
+        self.check_tmp_register(&instructions);
         let mut body: Vec<u8> = self.process_second_phase(&instructions);
+        if !self.errors.is_empty() {
+            return Err(self.errors.clone());
+        }
 
+        let debug_section = self
+            .debug_info()
+            .map_or(Vec::new(), |debug_info| debug_info.encode_to_bytes());
+        let mut assembled_program: Vec<u8> =
+            self.write_delf_header(body.len(), debug_section.len());
         assembled_program.append(&mut body);
+        assembled_program.extend_from_slice(&self.ro_section);
+        assembled_program.extend_from_slice(&debug_section);
         return Ok(assembled_program);
     }
 
@@ -114,25 +165,6 @@ impl Assembler {
         }
     }
 
-    fn process_label_declaration_second_phase(
-        &mut self,
-        instruction: &AssemblerInstruction,
-        offset: usize,
-    ) {
-        if instruction.token.is_some() {
-            match instruction.get_label_declaration_name() {
-                Some(name) => {
-                    if self.symbol_table.get_symbol(&name).is_some() {
-                        self.symbol_table.set_symbol_offset(&name, offset as u32);
-                    }
-                }
-                None => self.errors.push(NoLabelNameFound {
-                    instruction: self.current_instruction,
-                }),
-            }
-        }
-    }
-
     fn handle_asciiz(&mut self, instruction: &AssemblerInstruction) {
         if self.assemble_phase != AssemblerPhase::FIRST {
             return;
@@ -182,6 +214,42 @@ impl Assembler {
         }
     }
 
+    fn handle_table(&mut self, instruction: &AssemblerInstruction) {
+        if self.assemble_phase != AssemblerPhase::FIRST {
+            return;
+        }
+        match instruction.get_label_declaration_name() {
+            Some(name) => {
+                self.symbol_table.set_symbol_offset(&name, self.ro_offset);
+            }
+            None => {
+                self.errors.push(AssemblerError::LabelNotFoundForTable);
+            }
+        }
+        // entries are written once code labels have their offsets
+        let names = instruction.get_label_usage_list().unwrap();
+        self.jump_tables
+            .push((self.ro_section.len(), names.clone()));
+        for _ in 0..names.len() * 4 {
+            self.ro_section.push(0);
+        }
+        self.ro_offset += names.len() as u32 * 4;
+    }
+
+    fn fill_jump_tables(&mut self) {
+        for (offset, names) in self.jump_tables.clone() {
+            for (index, name) in names.iter().enumerate() {
+                match self.resolve_label(name) {
+                    Ok(value) => {
+                        let start = offset + index * 4;
+                        self.ro_section[start..start + 4].copy_from_slice(&value.to_be_bytes());
+                    }
+                    Err(e) => self.errors.push(e),
+                }
+            }
+        }
+    }
+
     pub(crate) fn process_section_header(&mut self, header_name: &str) {
         let new_section: AssemblerSection = header_name.into();
         if new_section == AssemblerSection::UnKnown {
@@ -201,6 +269,9 @@ impl Assembler {
                 "asciiz" => {
                     self.handle_asciiz(instruction);
                 }
+                "table" => {
+                    self.handle_table(instruction);
+                }
                 _ => {
                     self.errors.push(UnknownDirectiveFound {
                         directive: instruction.get_directive_name().unwrap().clone(),
@@ -212,31 +283,127 @@ impl Assembler {
         }
     }
 
-    fn process_label_usage(&mut self, instruction: &AssemblerInstruction) -> Vec<u8> {
+    /*
+     * opcodes taking a label as an immediate operand. A label used by any other opcode
+     * (PRTS, JMP, JL, JG, JMPF, JMPB...) is loaded into TMP_REGISTER first, so $31 is
+     * reserved for the assembler in programs passing labels to those, see check_tmp_register.
+     */
+    fn has_immediate_operand(opcode: OpCode) -> bool {
+        match opcode {
+            OpCode::LOAD
+            | OpCode::ADDI
+            | OpCode::SUBI
+            | OpCode::CMPI
+            | OpCode::JMPI
+            | OpCode::JEI
+            | OpCode::JNEI
+            | OpCode::CALLI
+            | OpCode::LDROW
+            | OpCode::LOADLOCAL
+            | OpCode::STORELOCAL => {
+                return true;
+            }
+            _ => {
+                return false;
+            }
+        }
+    }
+
+    fn resolve_label(&mut self, name: &str) -> Result<i32, AssemblerError> {
+        match self.symbol_table.get_symbol_offset(name) {
+            Some(offset) => {
+                return Ok(offset as i32);
+            }
+            None => {
+                return Err(UnknownLabel {
+                    name: name.to_string(),
+                });
+            }
+        }
+    }
+
+    /* a label in a 16 bits immediate must fit like a literal would, LOAD widens instead */
+    fn resolve_operand(
+        &mut self,
+        opcode: OpCode,
+        operand: &Option<Token>,
+    ) -> Result<Option<Token>, AssemblerError> {
+        match operand {
+            Some(Token::LabelUsage { name }) => {
+                let value = self.resolve_label(name)?;
+                let resolved = IntegerOperand { value };
+                let fits = match opcode {
+                    OpCode::ADDI
+                    | OpCode::SUBI
+                    | OpCode::CMPI
+                    | OpCode::LOADLOCAL
+                    | OpCode::STORELOCAL => InstructionParser::check_signed_immediate(&resolved),
+                    OpCode::LDROW => InstructionParser::check_unsigned_immediate(&resolved),
+                    _ => Ok(()),
+                };
+                if fits.is_err() {
+                    return Err(LabelOutOfRange {
+                        name: name.to_string(),
+                        offset: value,
+                    });
+                }
+                return Ok(Some(resolved));
+            }
+            _ => {
+                return Ok(operand.clone());
+            }
+        }
+    }
+
+    fn assemble_instruction(
+        &mut self,
+        instruction: &AssemblerInstruction,
+    ) -> Result<Vec<u8>, AssemblerError> {
+        if !instruction.is_label_usage() {
+            return Ok(instruction.to_bytes());
+        }
+        match instruction.token {
+            Some(Token::Op { opcode }) if Assembler::has_immediate_operand(opcode) => {
+                let mut resolved = instruction.clone();
+                resolved.set_operand1(self.resolve_operand(opcode, &instruction.operand1)?);
+                resolved.set_operand2(self.resolve_operand(opcode, &instruction.operand2)?);
+                resolved.set_operand3(self.resolve_operand(opcode, &instruction.operand3)?);
+                return Ok(resolved.to_bytes());
+            }
+            _ => {
+                return self.process_label_usage(instruction);
+            }
+        }
+    }
+
+    fn process_label_usage(
+        &mut self,
+        instruction: &AssemblerInstruction,
+    ) -> Result<Vec<u8>, AssemblerError> {
         let mut bytes = Vec::<u8>::new();
 
         match &instruction.operand1 {
             Some(Token::LabelUsage { name }) => {
-                self.process_label_offset(&instruction, &mut bytes, &name)
+                self.process_label_offset(&instruction, &mut bytes, &name)?
             }
             _ => {}
         }
 
         match &instruction.operand2 {
             Some(Token::LabelUsage { name }) => {
-                self.process_label_offset(&instruction, &mut bytes, &name)
+                self.process_label_offset(&instruction, &mut bytes, &name)?
             }
             _ => {}
         }
 
         match &instruction.operand3 {
             Some(Token::LabelUsage { name }) => {
-                self.process_label_offset(&instruction, &mut bytes, &name)
+                self.process_label_offset(&instruction, &mut bytes, &name)?
             }
             _ => {}
         }
 
-        return bytes;
+        return Ok(bytes);
     }
 
     fn process_label_offset(
@@ -244,8 +411,8 @@ impl Assembler {
         instruction: &AssemblerInstruction,
         bytes: &mut Vec<u8>,
         name: &&String,
-    ) -> () {
-        let offset = self.symbol_table.get_symbol_offset(&name).unwrap();
+    ) -> Result<(), AssemblerError> {
+        let offset = self.resolve_label(&name)?;
         let save_offset_instruction = AssemblerInstruction {
             token: Some(Op {
                 opcode: OpCode::LOAD,
@@ -255,9 +422,7 @@ impl Assembler {
             operand1: Some(Register {
                 reg_num: TMP_REGISTER,
             }),
-            operand2: Some(IntegerOperand {
-                value: offset as i32,
-            }),
+            operand2: Some(IntegerOperand { value: offset }),
             operand3: None,
         };
         bytes.append(&mut save_offset_instruction.to_bytes());
@@ -271,6 +436,7 @@ impl Assembler {
             }
         }
         bytes.push(TMP_REGISTER);
+        Ok(())
     }
 
     // scan symbol declaration to symbol table,and sections
@@ -294,22 +460,74 @@ impl Assembler {
         self.assemble_phase = AssemblerPhase::SECOND;
     }
 
+    // give code labels their offset, a label usage can pick a wider encoding (LOAD32)
+    // once its label moves past 65535, so repeat until no offset changes.
+    fn process_layout_phase(&mut self, instructions: &Vec<AssemblerInstruction>) {
+        loop {
+            let mut changed = false;
+            let mut in_code = false;
+            let mut offset = 0;
+            for instruction in instructions {
+                if instruction.is_directive() && !instruction.has_operands() {
+                    in_code = instruction.get_directive_name() == Some("code".to_string());
+                }
+                if in_code && instruction.is_label_declaration() && !instruction.is_directive() {
+                    let name = instruction.get_label_declaration_name().unwrap();
+                    self.symbol_table
+                        .set_symbol_type(&name, SymbolType::CodeLabel);
+                    if self.symbol_table.get_symbol_offset(&name) != Some(offset as u32) {
+                        self.symbol_table.set_symbol_offset(&name, offset as u32);
+                        changed = true;
+                    }
+                }
+                if instruction.is_opcode() {
+                    // unknown labels are reported by the second phase
+                    offset += self
+                        .assemble_instruction(&instruction)
+                        .map_or(0, |bytes| bytes.len());
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    // an instruction naming $31 would see it clobbered by the LOAD a label expands to
+    fn check_tmp_register(&mut self, instructions: &Vec<AssemblerInstruction>) {
+        let expands = instructions
+            .iter()
+            .any(|instruction| match instruction.token {
+                Some(Token::Op { opcode }) => {
+                    instruction.is_label_usage() && !Assembler::has_immediate_operand(opcode)
+                }
+                _ => false,
+            });
+        if !expands {
+            return;
+        }
+        for (index, instruction) in instructions.iter().enumerate() {
+            if instruction.is_opcode() && instruction.uses_register(TMP_REGISTER) {
+                self.errors.push(TmpRegisterInUse {
+                    instruction: index as u32,
+                });
+            }
+        }
+    }
+
     // translate symbol usage to memory offset
     fn process_second_phase(&mut self, instructions: &Vec<AssemblerInstruction>) -> Vec<u8> {
         self.current_instruction = 0;
         let mut program = Vec::<u8>::new();
 
+        self.debug_lines.clear();
         for instruction in instructions {
-            if instruction.is_label_declaration() {
-                self.process_label_declaration_second_phase(&instruction, program.len());
-            }
             if instruction.is_opcode() {
-                if instruction.is_label_usage() {
-                    let mut bytes = self.process_label_usage(&instruction);
-                    program.append(&mut bytes);
-                } else {
-                    let mut bytes = instruction.to_bytes();
-                    program.append(&mut bytes);
+                self.debug_lines
+                    .push((program.len(), self.current_instruction + 1));
+                match self.assemble_instruction(&instruction) {
+                    Ok(mut bytes) => program.append(&mut bytes),
+                    Err(e) => self.errors.push(e),
                 }
             }
 
@@ -13,11 +13,15 @@ pub enum AssemblerError {
     NoLabelNameFound { instruction: u32 },
     SymbolAlreadyDeclared { instruction: u32 },
     NoDirectiveNameFound { instruction: u32 },
+    TmpRegisterInUse { instruction: u32 },
     UnknownDirectiveFound { directive: String },
     UnknownSectionFound { section_name: String },
+    UnknownLabel { name: String },
+    LabelOutOfRange { name: String, offset: i32 },
     InsufficientSections,
     StringConstantNotFound,
     LabelNotFoundForStringConstant,
+    LabelNotFoundForTable,
 }
 
 impl Error for AssemblerError {
@@ -3,8 +3,9 @@
  */
 
 use crate::assembler::token::Token;
+use crate::vm::instruction::OpCode;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct AssemblerInstruction {
     pub token: Option<Token>,
     pub label: Option<Token>,
@@ -83,6 +84,15 @@ impl AssemblerInstruction {
         return label_usage;
     }
 
+    pub fn uses_register(&self, register: u8) -> bool {
+        return [&self.operand1, &self.operand2, &self.operand3].iter().any(
+            |operand| match operand {
+                Some(Token::Register { reg_num }) => *reg_num == register,
+                _ => false,
+            },
+        );
+    }
+
     pub fn is_opcode(&self) -> bool {
         return self.token.is_some();
     }
@@ -154,6 +164,17 @@ impl AssemblerInstruction {
         }
     }
 
+    pub fn get_label_usage_list(&self) -> Option<Vec<String>> {
+        match &self.operand1 {
+            Some(Token::LabelUsageList { names }) => {
+                return Some(names.clone());
+            }
+            _ => {
+                return None;
+            }
+        }
+    }
+
     pub fn is_directive(&self) -> bool {
         return self.directive.is_some();
     }
@@ -174,17 +195,46 @@ impl AssemblerInstruction {
         self.operand3 = token;
     }
 
+    /* LOAD only carries 16 bits unsigned, anything else needs LOAD32 */
+    fn needs_wide_load(&self) -> bool {
+        match self.operand2 {
+            Some(Token::IntegerOperand { value }) => {
+                return value < 0 || value > u16::max_value() as i32;
+            }
+            _ => {
+                return false;
+            }
+        }
+    }
+
+    /* immediates of these opcodes are encoded in 32 bits, everything else in 16 bits */
+    fn has_wide_immediate(opcode: OpCode) -> bool {
+        match opcode {
+            OpCode::LOAD32 | OpCode::JMPI | OpCode::JEI | OpCode::JNEI | OpCode::CALLI => {
+                return true;
+            }
+            _ => {
+                return false;
+            }
+        }
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut results: Vec<u8> = Vec::new();
-        match self.token {
-            Some(Token::Op { opcode }) => match opcode {
-                _ => results.push(opcode as u8),
-            },
+        let opcode = match self.token {
+            Some(Token::Op { opcode }) => {
+                if opcode == OpCode::LOAD && self.needs_wide_load() {
+                    OpCode::LOAD32
+                } else {
+                    opcode
+                }
+            }
             _ => {
                 println!("None opCode found in opCode field.");
                 std::process::exit(0);
             }
         };
+        results.push(opcode as u8);
 
         for operand in vec![&self.operand1, &self.operand2, &self.operand3] {
             match operand {
@@ -192,6 +242,13 @@ impl AssemblerInstruction {
                     Token::Register { reg_num } => {
                         results.push(*reg_num);
                     }
+                    Token::IntegerOperand { value }
+                        if AssemblerInstruction::has_wide_immediate(opcode) =>
+                    {
+                        for byte in value.to_be_bytes().iter() {
+                            results.push(*byte);
+                        }
+                    }
                     Token::IntegerOperand { value } => {
                         let converted = *value as u16;
                         let byte1 = converted;
@@ -0,0 +1,162 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+use crate::assembler::elf::DELFError;
+use crate::assembler::elf::DELFError::BadDebugInfo;
+
+/*
+ * DELF debug section, numbers are big-endian u32, a string is its length and UTF-8 bytes:
+ *
+ *   source file name
+ *   code label count, per label: offset, name
+ *   data label count, per label: offset, name
+ *   line count, per instruction: pc, source line
+ */
+#[derive(Debug, PartialEq, Clone)]
+pub struct DebugInfo {
+    pub source: String,
+    /* program offsets of the .code labels, sorted by offset */
+    pub code_labels: Vec<(String, usize)>,
+    /* ro_data offsets of the .data labels, sorted by offset */
+    pub data_labels: Vec<(String, usize)>,
+    /* (pc, source line) of every instruction, sorted by pc */
+    pub lines: Vec<(usize, u32)>,
+}
+
+struct DebugReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> DebugReader<'a> {
+    fn u32(&mut self) -> Result<u32, DELFError> {
+        if self.bytes.len() - self.offset < 4 {
+            return Err(BadDebugInfo {
+                offset: self.offset,
+            });
+        }
+        let mut value = [0; 4];
+        value.copy_from_slice(&self.bytes[self.offset..self.offset + 4]);
+        self.offset += 4;
+        return Ok(u32::from_be_bytes(value));
+    }
+
+    fn string(&mut self) -> Result<String, DELFError> {
+        let start = self.offset;
+        let length = self.u32()? as usize;
+        if self.bytes.len() - self.offset < length {
+            return Err(BadDebugInfo { offset: start });
+        }
+        let bytes = &self.bytes[self.offset..self.offset + length];
+        self.offset += length;
+        return String::from_utf8(bytes.to_vec()).map_err(|_| BadDebugInfo { offset: start });
+    }
+
+    fn labels(&mut self) -> Result<Vec<(String, usize)>, DELFError> {
+        let count = self.u32()?;
+        let mut labels = Vec::new();
+        for _ in 0..count {
+            let offset = self.u32()? as usize;
+            labels.push((self.string()?, offset));
+        }
+        return Ok(labels);
+    }
+}
+
+fn write_string(bytes: &mut Vec<u8>, value: &str) {
+    bytes.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+fn write_labels(bytes: &mut Vec<u8>, labels: &[(String, usize)]) {
+    bytes.extend_from_slice(&(labels.len() as u32).to_be_bytes());
+    for (name, offset) in labels {
+        bytes.extend_from_slice(&(*offset as u32).to_be_bytes());
+        write_string(bytes, name);
+    }
+}
+
+impl DebugInfo {
+    pub fn new(source: &str) -> DebugInfo {
+        DebugInfo {
+            source: source.to_string(),
+            code_labels: Vec::new(),
+            data_labels: Vec::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn encode_to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_string(&mut bytes, &self.source);
+        write_labels(&mut bytes, &self.code_labels);
+        write_labels(&mut bytes, &self.data_labels);
+        bytes.extend_from_slice(&(self.lines.len() as u32).to_be_bytes());
+        for (pc, line) in &self.lines {
+            bytes.extend_from_slice(&(*pc as u32).to_be_bytes());
+            bytes.extend_from_slice(&line.to_be_bytes());
+        }
+        return bytes;
+    }
+
+    pub fn decode_from_bytes(bytes: &[u8]) -> Result<DebugInfo, DELFError> {
+        let mut reader = DebugReader { bytes, offset: 0 };
+        let source = reader.string()?;
+        let mut code_labels = reader.labels()?;
+        let mut data_labels = reader.labels()?;
+        let count = reader.u32()?;
+        let mut lines = Vec::new();
+        for _ in 0..count {
+            let pc = reader.u32()? as usize;
+            lines.push((pc, reader.u32()?));
+        }
+        if reader.offset != bytes.len() {
+            return Err(BadDebugInfo {
+                offset: reader.offset,
+            });
+        }
+        code_labels.sort_by_key(|label| label.1);
+        data_labels.sort_by_key(|label| label.1);
+        lines.sort_by_key(|line| line.0);
+        return Ok(DebugInfo {
+            source,
+            code_labels,
+            data_labels,
+            lines,
+        });
+    }
+
+    /* source line of the instruction `pc` is in */
+    pub fn line(&self, pc: usize) -> Option<u32> {
+        return self
+            .lines
+            .iter()
+            .rev()
+            .find(|(start, _)| *start <= pc)
+            .map(|(_, line)| *line);
+    }
+
+    /* the closest code label at or before `pc` and the distance from it */
+    pub fn label(&self, pc: usize) -> Option<(&str, usize)> {
+        return self
+            .code_labels
+            .iter()
+            .rev()
+            .find(|(_, offset)| *offset <= pc)
+            .map(|(name, offset)| (name.as_str(), pc - offset));
+    }
+
+    /* `for+3 (for_each.asm:6)`, falling back to `pc=14` for the parts that are unknown */
+    pub fn location(&self, pc: usize) -> String {
+        let position = match self.label(pc) {
+            Some((name, 0)) => name.to_string(),
+            Some((name, distance)) => format!("{}+{}", name, distance),
+            None => format!("pc={}", pc),
+        };
+        match self.line(pc) {
+            Some(line) => format!("{} ({}:{})", position, self.source, line),
+            None => position,
+        }
+    }
+}
@@ -0,0 +1,448 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+use crate::assembler::debug_info::DebugInfo;
+use crate::assembler::disassembler::DataItem::{Bytes, StringConstant, Table};
+use crate::assembler::elf::{DELFError, DELFHeader};
+use crate::vm::instruction::OpCode::{
+    CALL, HLTR, IGL, JE, JG, JL, JMP, JNE, LDROW, LOAD, LOAD32, PRTS,
+};
+use crate::vm::instruction::OperandKind::{Immediate16, Immediate32, Register};
+use crate::vm::instruction::{OpCode, OperandKind};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::str::from_utf8;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct DisassembledInstruction {
+    pub address: usize,
+    pub opcode: OpCode,
+    /* decoded with the layout of OpCode::operands, immediates as raw unsigned values */
+    pub operands: Vec<(OperandKind, u32)>,
+    /* what a label address loaded for the next instruction refers to, e.g. `@done` */
+    pub comment: Option<String>,
+    /* the immediate is an ro_data offset, printed as `@label` when a data label starts there */
+    pub data_operand: bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum DataItem {
+    StringConstant {
+        offset: usize,
+        label: Option<String>,
+        text: String,
+    },
+    /* a .table, one program address per entry */
+    Table {
+        offset: usize,
+        label: Option<String>,
+        entries: Vec<u32>,
+    },
+    Bytes {
+        offset: usize,
+        label: Option<String>,
+        bytes: Vec<u8>,
+    },
+}
+
+/* turns a code section and its ro_data back into assembly, naming labels when they are known */
+#[derive(Debug, Clone)]
+pub struct Disassembler {
+    code: Vec<u8>,
+    ro_data: Vec<u8>,
+    entry: usize,
+    code_labels: Vec<(String, usize)>,
+    data_labels: Vec<(String, usize)>,
+    debug_info: Option<DebugInfo>,
+}
+
+/* disassemble a DELF file, naming labels and source lines when it has a debug section */
+pub fn disassemble(program: &[u8]) -> Result<String, DELFError> {
+    return Ok(Disassembler::from_delf(program)?.to_string());
+}
+
+impl DisassembledInstruction {
+    /* the assembler picks LOAD32 and HLTR from the operands of `load` and `hlt` */
+    pub fn mnemonic(&self) -> String {
+        match self.opcode {
+            LOAD32 => "load".to_string(),
+            HLTR => "hlt".to_string(),
+            opcode => format!("{:?}", opcode).to_lowercase(),
+        }
+    }
+
+    fn length(&self) -> usize {
+        return 1 + self.opcode.operand_length();
+    }
+}
+
+impl Disassembler {
+    pub fn new(code: Vec<u8>, ro_data: Vec<u8>) -> Disassembler {
+        Disassembler {
+            code,
+            ro_data,
+            entry: 0,
+            code_labels: Vec::new(),
+            data_labels: Vec::new(),
+            debug_info: None,
+        }
+    }
+
+    pub fn from_delf(program: &[u8]) -> Result<Disassembler, DELFError> {
+        let header = DELFHeader::decode_from_bytes(program)?;
+        header.check_sections(program.len())?;
+        let code_start = header.code_offset as usize;
+        let ro_data_start = header.ro_data_offset as usize;
+        let mut disassembler = Disassembler::new(
+            program[code_start..code_start + header.code_length as usize].to_vec(),
+            program[ro_data_start..ro_data_start + header.ro_data_length as usize].to_vec(),
+        );
+        disassembler.entry = header.entry as usize;
+        if header.debug_length > 0 {
+            let start = header.debug_offset as usize;
+            let debug_info = DebugInfo::decode_from_bytes(
+                &program[start..start + header.debug_length as usize],
+            )?;
+            disassembler.set_labels(
+                debug_info.code_labels.clone(),
+                debug_info.data_labels.clone(),
+            );
+            disassembler.debug_info = Some(debug_info);
+        }
+        return Ok(disassembler);
+    }
+
+    /* code labels are program offsets, data labels ro_data offsets, see SymbolTable */
+    pub fn set_labels(
+        &mut self,
+        code_labels: Vec<(String, usize)>,
+        data_labels: Vec<(String, usize)>,
+    ) {
+        self.code_labels = code_labels;
+        self.code_labels.sort_by_key(|label| label.1);
+        self.data_labels = data_labels;
+        self.data_labels.sort_by_key(|label| label.1);
+    }
+
+    fn code_label(&self, address: usize) -> Option<&String> {
+        return self
+            .code_labels
+            .iter()
+            .find(|(_, offset)| *offset == address)
+            .map(|(name, _)| name);
+    }
+
+    fn data_label(&self, offset: usize) -> Option<&String> {
+        return self
+            .data_labels
+            .iter()
+            .find(|(_, label_offset)| *label_offset == offset)
+            .map(|(name, _)| name);
+    }
+
+    /* decode the whole code section, stops before an IGL or a truncated instruction */
+    pub fn instructions(&self) -> Vec<DisassembledInstruction> {
+        let mut instructions: Vec<DisassembledInstruction> = Vec::new();
+        let mut pc = 0;
+        while pc < self.code.len() {
+            let opcode = OpCode::from(self.code[pc]);
+            if opcode == IGL || pc + 1 + opcode.operand_length() > self.code.len() {
+                break;
+            }
+            let mut operands = Vec::new();
+            let mut offset = pc + 1;
+            for kind in opcode.operands() {
+                let value = self.code[offset..offset + kind.length()]
+                    .iter()
+                    .fold(0, |value, byte| (value << 8) | *byte as u32);
+                operands.push((*kind, value));
+                offset += kind.length();
+            }
+            instructions.push(DisassembledInstruction {
+                address: pc,
+                opcode,
+                operands,
+                comment: None,
+                data_operand: opcode == LDROW,
+            });
+            pc = offset;
+        }
+
+        // the assembler passes `@label` to register operands as LOAD $31 #offset
+        for index in 1..instructions.len() {
+            let next = &instructions[index];
+            let target = match next.operands.first() {
+                Some((Register, register)) => *register,
+                _ => continue,
+            };
+            let comment = match &instructions[index - 1] {
+                load if (load.opcode == LOAD || load.opcode == LOAD32)
+                    && load.operands[0].1 == target =>
+                {
+                    self.describe_address(next.opcode, load.operands[1].1 as usize)
+                }
+                _ => None,
+            };
+            let loads_string = next.opcode == PRTS && comment.is_some();
+            instructions[index - 1].comment = comment;
+            instructions[index - 1].data_operand |= loads_string;
+        }
+        return instructions;
+    }
+
+    fn describe_address(&self, opcode: OpCode, address: usize) -> Option<String> {
+        match opcode {
+            PRTS => self
+                .string_at(address)
+                .map(|text| format!("\"{}\"", escape(&text))),
+            JMP | JE | JNE | JL | JG | CALL => {
+                self.code_label(address).map(|name| format!("@{}", name))
+            }
+            _ => None,
+        }
+    }
+
+    /* the NUL terminated UTF-8 string PRTS would print from `offset` */
+    fn string_at(&self, offset: usize) -> Option<String> {
+        let rest = self.ro_data.get(offset..)?;
+        let end = rest.iter().position(|byte| *byte == 0)?;
+        let text = from_utf8(&rest[..end]).ok()?;
+        if text
+            .chars()
+            .any(|c| c.is_control() && c != '\n' && c != '\t')
+        {
+            return None;
+        }
+        return Some(text.to_string());
+    }
+
+    /*
+     * ro_data split at the data labels, or into NUL terminated strings without them.
+     * A labeled item that is no string is a .table when its words are code addresses.
+     */
+    pub fn data(&self) -> Vec<DataItem> {
+        let mut starts: Vec<usize> = self
+            .data_labels
+            .iter()
+            .map(|(_, offset)| *offset)
+            .filter(|offset| *offset < self.ro_data.len())
+            .collect();
+        if starts.first() != Some(&0) {
+            starts.insert(0, 0);
+        }
+        starts.dedup();
+
+        let mut items = Vec::new();
+        for (index, start) in starts.iter().enumerate() {
+            let end = starts.get(index + 1).map_or(self.ro_data.len(), |end| *end);
+            let mut offset = *start;
+            while offset < end {
+                let label = self.data_label(offset).cloned();
+                let item_end = match self.string_at(offset) {
+                    // with labels every labeled item is a single string or table
+                    Some(ref text)
+                        if offset + text.len() + 1 == end
+                            || (self.data_labels.is_empty() && offset + text.len() + 1 < end) =>
+                    {
+                        let item_end = offset + text.len() + 1;
+                        items.push(StringConstant {
+                            offset,
+                            label,
+                            text: text.clone(),
+                        });
+                        item_end
+                    }
+                    _ => {
+                        items.push(self.binary_item(offset, end, label));
+                        end
+                    }
+                };
+                offset = item_end;
+            }
+        }
+        return items;
+    }
+
+    fn binary_item(&self, offset: usize, end: usize, label: Option<String>) -> DataItem {
+        let bytes = &self.ro_data[offset..end];
+        let entries: Vec<u32> = bytes
+            .chunks(4)
+            .map(|word| {
+                word.iter()
+                    .fold(0, |value, byte| (value << 8) | *byte as u32)
+            })
+            .collect();
+        if bytes.len() % 4 == 0
+            && entries
+                .iter()
+                .all(|entry| self.code_label(*entry as usize).is_some())
+        {
+            return Table {
+                offset,
+                label,
+                entries,
+            };
+        }
+        return Bytes {
+            offset,
+            label,
+            bytes: bytes.to_vec(),
+        };
+    }
+
+    fn format_operand(
+        &self,
+        instruction: &DisassembledInstruction,
+        kind: OperandKind,
+        value: u32,
+    ) -> String {
+        if instruction.data_operand && kind != Register {
+            if let Some(name) = self.data_label(value as usize) {
+                return format!("@{}", name);
+            }
+        }
+        match kind {
+            Register => format!("${}", value),
+            Immediate16 if instruction.opcode.has_signed_immediate() => {
+                format!("#{}", value as u16 as i16)
+            }
+            Immediate16 => format!("#{}", value),
+            Immediate32 if instruction.opcode.has_signed_immediate() => {
+                format!("#{}", value as i32)
+            }
+            Immediate32 => match self.code_label(value as usize) {
+                Some(name) => format!("@{}", name),
+                None => format!("#{}", value),
+            },
+        }
+    }
+
+    pub fn format_instruction(&self, instruction: &DisassembledInstruction) -> String {
+        let mut line = format!("{:04}  {}", instruction.address, instruction.mnemonic());
+        for (kind, value) in &instruction.operands {
+            line.push(' ');
+            line.push_str(&self.format_operand(instruction, *kind, *value));
+        }
+        if let Some(ref comment) = instruction.comment {
+            line = format!("{:<28}; {}", line, comment);
+        }
+        return line;
+    }
+}
+
+fn escape(text: &str) -> String {
+    return text.replace('\n', "\\n").replace('\t', "\\t");
+}
+
+fn labeled(label: &Option<String>) -> String {
+    match label {
+        Some(name) => format!("{}: ", name),
+        None => String::new(),
+    }
+}
+
+impl Display for DataItem {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            StringConstant {
+                offset,
+                label,
+                text,
+            } => f.write_str(&format!(
+                "{:04}  {}.asciiz \"{}\"",
+                offset,
+                labeled(label),
+                escape(text)
+            )),
+            Table {
+                offset,
+                label,
+                entries,
+            } => {
+                let entries: Vec<String> =
+                    entries.iter().map(|entry| format!("#{}", entry)).collect();
+                f.write_str(&format!(
+                    "{:04}  {}.table {}",
+                    offset,
+                    labeled(label),
+                    entries.join(" ")
+                ))
+            }
+            Bytes {
+                offset,
+                label,
+                bytes,
+            } => {
+                let bytes: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+                f.write_str(&format!(
+                    "{:04}  {}; {}",
+                    offset,
+                    labeled(label),
+                    bytes.join(" ")
+                ))
+            }
+        }
+    }
+}
+
+impl Display for Disassembler {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let mut lines = vec![".data".to_string()];
+        for item in self.data() {
+            lines.push(match item {
+                Table {
+                    offset,
+                    label,
+                    entries,
+                } => {
+                    let names: Vec<String> = entries
+                        .iter()
+                        .map(|entry| format!("@{}", self.code_label(*entry as usize).unwrap()))
+                        .collect();
+                    format!(
+                        "{:04}  {}.table {}",
+                        offset,
+                        labeled(&label),
+                        names.join(" ")
+                    )
+                }
+                item => item.to_string(),
+            });
+        }
+
+        lines.push(".code".to_string());
+        let instructions = self.instructions();
+        let mut source_line = None;
+        for instruction in &instructions {
+            if let Some(name) = self.code_label(instruction.address) {
+                lines.push(format!("{}:", name));
+            } else if instruction.address == self.entry && self.entry != 0 {
+                lines.push("; entry".to_string());
+            }
+            if let Some(ref debug_info) = self.debug_info {
+                let line = debug_info.line(instruction.address);
+                if line.is_some() && line != source_line {
+                    lines.push(format!("; {}:{}", debug_info.source, line.unwrap()));
+                }
+                source_line = line;
+            }
+            lines.push(self.format_instruction(instruction));
+        }
+        let decoded = instructions
+            .last()
+            .map_or(0, |last| last.address + last.length());
+        if decoded < self.code.len() {
+            let bytes: Vec<String> = self.code[decoded..]
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect();
+            lines.push(format!(
+                "{:04}  ; not an instruction: {}",
+                decoded,
+                bytes.join(" ")
+            ));
+        }
+        f.write_str(&lines.join("\n"))
+    }
+}
@@ -1,48 +1,241 @@
+use crate::assembler::elf::DELFError::{
+    BadDebugInfo, BadHeaderSize, BadMagic, EntryOutOfBounds, Rejected, SectionOutOfBounds,
+    TooShort, UnsupportedVersion,
+};
+use crate::vm::vm_error::VmError;
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
 pub const ELF_HEADER_PREFIX: [u8; 4] = [0x64, 0x65, 0x6c, 0x66];
 pub const ELF_HEADER_LENGTH: usize = 64;
+pub const DELF_VERSION: u32 = 1;
+
+/*
+ * DELF header, ELF_HEADER_LENGTH bytes, every field a big-endian u32:
+ *
+ *   0   identify, ELF_HEADER_PREFIX
+ *   4   version
+ *   8   header_size
+ *   12  entry, pc of the first instruction to run (an offset into the code section)
+ *   16  code_offset      20  code_length
+ *   24  ro_data_offset   28  ro_data_length
+ *   32  debug_offset     36  debug_length, both 0 without a debug section, see DebugInfo
+ *   40  reserved, zero up to header_size
+ *
+ * Offsets count from the start of the file, the assembler writes header, code, ro_data, debug.
+ */
+#[derive(Debug, PartialEq, Clone)]
+pub enum DELFError {
+    TooShort {
+        length: usize,
+    },
+    BadMagic,
+    UnsupportedVersion {
+        version: u32,
+    },
+    BadHeaderSize {
+        size: u32,
+    },
+    SectionOutOfBounds {
+        section: &'static str,
+        offset: u32,
+        length: u32,
+        file_length: usize,
+    },
+    EntryOutOfBounds {
+        entry: u32,
+        code_length: u32,
+    },
+    BadDebugInfo {
+        offset: usize,
+    },
+    /* the code section failed vm::verifier, with the fault it would raise */
+    Rejected {
+        error: VmError,
+    },
+}
 
+#[derive(Debug, PartialEq, Clone)]
 pub struct DELFHeader {
-    identify: [u8; 4],
-    version: u32,
-    entry: u32,
-    program_header_offset: u32,
-    segment_header_offset: u32,
-    header_size: usize,
-    program_header_length: u32,
-    segment_header_length: u32,
-    segment_str_table_index: u32,
+    pub identify: [u8; 4],
+    pub version: u32,
+    pub header_size: u32,
+    pub entry: u32,
+    pub code_offset: u32,
+    pub code_length: u32,
+    pub ro_data_offset: u32,
+    pub ro_data_length: u32,
+    pub debug_offset: u32,
+    pub debug_length: u32,
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut value = [0; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    return u32::from_be_bytes(value);
 }
 
 impl DELFHeader {
     pub fn new() -> DELFHeader {
         DELFHeader {
             identify: ELF_HEADER_PREFIX,
-            version: 0,
+            version: DELF_VERSION,
+            header_size: ELF_HEADER_LENGTH as u32,
             entry: 0,
-            program_header_offset: 0,
-            segment_header_offset: 0,
-            header_size: ELF_HEADER_LENGTH,
-            program_header_length: 0,
-            segment_header_length: 0,
-            segment_str_table_index: 0,
+            code_offset: ELF_HEADER_LENGTH as u32,
+            code_length: 0,
+            ro_data_offset: ELF_HEADER_LENGTH as u32,
+            ro_data_length: 0,
+            debug_offset: 0,
+            debug_length: 0,
         }
     }
 
-    fn encode_to_bytes(&self) -> Vec<u8> {
-        return Vec::new();
+    /* header for a file laid out as header, code, ro_data */
+    pub fn for_sections(entry: u32, code_length: u32, ro_data_length: u32) -> DELFHeader {
+        let mut header = DELFHeader::new();
+        header.entry = entry;
+        header.code_length = code_length;
+        header.ro_data_offset = header.code_offset + code_length;
+        header.ro_data_length = ro_data_length;
+        return header;
     }
 
-    fn decode_from_bytes(headers: Vec<u8>) -> DELFHeader {
-        DELFHeader {
+    /* a debug section of `debug_length` bytes after ro_data */
+    pub fn with_debug_section(mut self, debug_length: u32) -> DELFHeader {
+        self.debug_offset = self.ro_data_offset + self.ro_data_length;
+        self.debug_length = debug_length;
+        return self;
+    }
+
+    pub fn encode_to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.identify.to_vec();
+        for field in [
+            self.version,
+            self.header_size,
+            self.entry,
+            self.code_offset,
+            self.code_length,
+            self.ro_data_offset,
+            self.ro_data_length,
+            self.debug_offset,
+            self.debug_length,
+        ]
+        .iter()
+        {
+            bytes.extend_from_slice(&field.to_be_bytes());
+        }
+        bytes.resize(ELF_HEADER_LENGTH, 0);
+        return bytes;
+    }
+
+    pub fn decode_from_bytes(bytes: &[u8]) -> Result<DELFHeader, DELFError> {
+        if bytes.len() >= 4 && bytes[0..4] != ELF_HEADER_PREFIX {
+            return Err(BadMagic);
+        }
+        if bytes.len() < ELF_HEADER_LENGTH {
+            return Err(TooShort {
+                length: bytes.len(),
+            });
+        }
+        let version = read_u32(bytes, 4);
+        if version != DELF_VERSION {
+            return Err(UnsupportedVersion { version });
+        }
+        let header_size = read_u32(bytes, 8);
+        if header_size as usize != ELF_HEADER_LENGTH {
+            return Err(BadHeaderSize { size: header_size });
+        }
+        return Ok(DELFHeader {
             identify: ELF_HEADER_PREFIX,
-            version: 0,
-            entry: 0,
-            program_header_offset: 0,
-            segment_header_offset: 0,
-            header_size: ELF_HEADER_LENGTH,
-            program_header_length: 0,
-            segment_header_length: 0,
-            segment_str_table_index: 0,
+            version,
+            header_size,
+            entry: read_u32(bytes, 12),
+            code_offset: read_u32(bytes, 16),
+            code_length: read_u32(bytes, 20),
+            ro_data_offset: read_u32(bytes, 24),
+            ro_data_length: read_u32(bytes, 28),
+            debug_offset: read_u32(bytes, 32),
+            debug_length: read_u32(bytes, 36),
+        });
+    }
+
+    /* sections must lie inside the file after the header, the entry inside the code */
+    /* a missing debug section is 0, 0 */
+    pub fn check_sections(&self, file_length: usize) -> Result<(), DELFError> {
+        for (section, offset, length) in [
+            ("code", self.code_offset, self.code_length),
+            ("ro_data", self.ro_data_offset, self.ro_data_length),
+            ("debug", self.debug_offset, self.debug_length),
+        ]
+        .iter()
+        {
+            if *section == "debug" && *length == 0 {
+                continue;
+            }
+            let end = *offset as u64 + *length as u64;
+            if (*offset as usize) < ELF_HEADER_LENGTH || end > file_length as u64 {
+                return Err(SectionOutOfBounds {
+                    section,
+                    offset: *offset,
+                    length: *length,
+                    file_length,
+                });
+            }
+        }
+        if self.entry > self.code_length || (self.entry == self.code_length && self.entry != 0) {
+            return Err(EntryOutOfBounds {
+                entry: self.entry,
+                code_length: self.code_length,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Error for DELFError {
+    fn description(&self) -> &str {
+        match self {
+            _ => "DELF Error:",
+        }
+    }
+}
+
+impl Display for DELFError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            TooShort { ref length } => f.write_str(&format!(
+                "File of {} bytes is too short for a DELF header",
+                length
+            )),
+            BadMagic => f.write_str("Not a DELF file, bad magic"),
+            UnsupportedVersion { ref version } => {
+                f.write_str(&format!("Unsupported DELF version {}", version))
+            }
+            BadHeaderSize { ref size } => f.write_str(&format!("Bad DELF header size {}", size)),
+            SectionOutOfBounds {
+                ref section,
+                ref offset,
+                ref length,
+                ref file_length,
+            } => f.write_str(&format!(
+                "Section {} at {} with {} bytes is outside of the {} bytes file",
+                section, offset, length, file_length
+            )),
+            EntryOutOfBounds {
+                ref entry,
+                ref code_length,
+            } => f.write_str(&format!(
+                "Entry {} is outside of the {} bytes code section",
+                entry, code_length
+            )),
+            BadDebugInfo { ref offset } => {
+                f.write_str(&format!("Bad DELF debug section at byte {}", offset))
+            }
+            Rejected { ref error } => {
+                f.write_str(&format!("Program rejected by verifier: {}", error))
+            }
         }
     }
 }
@@ -3,8 +3,9 @@
  */
 
 use crate::assembler::assembler_instruction::AssemblerInstruction;
+use crate::assembler::token::Token;
 use crate::assembler::token::Token::{
-    Directive, IntegerOperand, IrString, LabelDeclaration, LabelUsage, Op, Register,
+    Directive, IntegerOperand, IrString, LabelDeclaration, LabelUsage, LabelUsageList, Op, Register,
 };
 use crate::vm::instruction::OpCode;
 use crate::vm::instruction::OpCode::*;
@@ -193,6 +194,186 @@ impl<'a> InstructionParser<'a> {
         }
     }
 
+    fn parse_register_immediate_instruction(
+        &mut self,
+        op: OpCode,
+    ) -> Result<AssemblerInstruction, &'static str> {
+        let instruction = self.parse_one_register_instruction(op)?;
+        self.tokens.next();
+        let immediate =
+            self.parse_checked_immediate(false, InstructionParser::check_unsigned_immediate)?;
+        return Ok(AssemblerInstruction::new(
+            instruction.token,
+            None,
+            None,
+            instruction.operand1,
+            Some(immediate),
+            None,
+        ));
+    }
+
+    /* true when the operand `index` tokens ahead is an immediate (#1) or a label (@hello) */
+    fn is_immediate_operand_at(&self, index: usize) -> bool {
+        return self.tokens.clone().nth(index).map_or(false, |word| {
+            word.starts_with(SYMBOL_LABEL_IMMEDIATE) || word.starts_with(SYMBOL_LABEL_USAGE)
+        });
+    }
+
+    /* parse and consume an immediate (#1) or a label (@hello) */
+    fn parse_immediate_operand(&mut self) -> Result<Token, &'static str> {
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| word.starts_with(SYMBOL_LABEL_IMMEDIATE))
+        {
+            let operand_str = &(*self.tokens.peek().unwrap().to_string())[1..];
+            let is_i32 = operand_str.parse::<i32>();
+            self.tokens.next();
+            match is_i32 {
+                Ok(operand) => {
+                    return Ok(IntegerOperand { value: operand });
+                }
+                Err(_e) => {
+                    return Err("An Integer is expected(e.g. #1)");
+                }
+            }
+        } else if self
+            .tokens
+            .peek()
+            .map_or(false, |word| word.starts_with(SYMBOL_LABEL_USAGE))
+        {
+            let label = self.parse_label_usage()?;
+            self.tokens.next();
+            return Ok(label.operand1.unwrap());
+        } else {
+            return Err("An Immediate number / Label is expected(e.g. #1 / @hello)");
+        }
+    }
+
+    /* parse and consume an immediate checked by `check`, a label only when `allow_label` */
+    fn parse_checked_immediate(
+        &mut self,
+        allow_label: bool,
+        check: fn(&Token) -> Result<(), &'static str>,
+    ) -> Result<Token, &'static str> {
+        if !allow_label
+            && !self
+                .tokens
+                .peek()
+                .map_or(false, |word| word.starts_with(SYMBOL_LABEL_IMMEDIATE))
+        {
+            return Err("An Immediate number is expected(e.g. #1)");
+        }
+        let immediate = self.parse_immediate_operand()?;
+        check(&immediate)?;
+        return Ok(immediate);
+    }
+
+    /* 16 bits immediates the vm reads as unsigned, e.g. heap offsets and LOADF64 */
+    pub(crate) fn check_unsigned_immediate(operand: &Token) -> Result<(), &'static str> {
+        match operand {
+            IntegerOperand { value } if *value < 0 || *value > u16::max_value() as i32 => {
+                return Err("An Unsigned Integer is expected(e.g. 1...65535)");
+            }
+            _ => {
+                return Ok(());
+            }
+        }
+    }
+
+    /* 16 bits immediates are sign extended by the vm */
+    pub(crate) fn check_signed_immediate(operand: &Token) -> Result<(), &'static str> {
+        match operand {
+            IntegerOperand { value }
+                if *value < i16::min_value() as i32 || *value > i16::max_value() as i32 =>
+            {
+                return Err("An Integer is expected(e.g. -32768...32767)");
+            }
+            _ => {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_register_immediate_register_instruction(
+        &mut self,
+        op: OpCode,
+    ) -> Result<AssemblerInstruction, &'static str> {
+        let instruction = self.parse_register_immediate_operand_instruction(op)?;
+        let target = self.parse_one_register_instruction(op)?;
+        return Ok(AssemblerInstruction::new(
+            instruction.token,
+            None,
+            None,
+            instruction.operand1,
+            instruction.operand2,
+            target.operand1,
+        ));
+    }
+
+    fn parse_register_immediate_operand_instruction(
+        &mut self,
+        op: OpCode,
+    ) -> Result<AssemblerInstruction, &'static str> {
+        let instruction = self.parse_one_register_instruction(op)?;
+        self.tokens.next();
+        let immediate =
+            self.parse_checked_immediate(true, InstructionParser::check_signed_immediate)?;
+        return Ok(AssemblerInstruction::new(
+            instruction.token,
+            None,
+            None,
+            instruction.operand1,
+            Some(immediate),
+            None,
+        ));
+    }
+
+    fn parse_two_register_immediate_operand_instruction(
+        &mut self,
+        op: OpCode,
+    ) -> Result<AssemblerInstruction, &'static str> {
+        let instruction = self.parse_two_register_instruction(op)?;
+        self.tokens.next();
+        // only LDROW offsets are ro_data, where a label can point
+        let offset =
+            self.parse_checked_immediate(op == LDROW, InstructionParser::check_unsigned_immediate)?;
+        return Ok(AssemblerInstruction::new(
+            instruction.token,
+            None,
+            None,
+            instruction.operand1,
+            instruction.operand2,
+            Some(offset),
+        ));
+    }
+
+    fn parse_immediate_instruction(
+        &mut self,
+        op: OpCode,
+    ) -> Result<AssemblerInstruction, &'static str> {
+        let address = self.parse_immediate_operand()?;
+        /* a syscall number is not an address, a label would assemble to an arbitrary syscall */
+        if op == SYSCALL {
+            if let LabelUsage { .. } = address {
+                return Err("An Immediate number is expected(e.g. #1)");
+            }
+        }
+        if let IntegerOperand { value } = address {
+            if value < 0 {
+                return Err("An Unsigned Integer is expected(e.g. #0)");
+            }
+        }
+        return Ok(AssemblerInstruction::new(
+            Some(Op { opcode: op }),
+            None,
+            None,
+            Some(address),
+            None,
+            None,
+        ));
+    }
+
     pub fn parse_directive(&mut self) -> Result<AssemblerInstruction, &'static str> {
         // directive : http://web.mit.edu/gnu/doc/html/as_7.html
         let directive = &(*self.tokens.peek().unwrap().to_string())[1..];
@@ -263,6 +444,32 @@ impl<'a> InstructionParser<'a> {
                 }
                 return Err("Expect a string starts with \" and end with \"");
             }
+            "table" => {
+                // table: .table @case0 @case1, a word per label holding its code offset
+                self.tokens.next();
+                let mut names: Vec<String> = Vec::new();
+                while self
+                    .tokens
+                    .peek()
+                    .map_or(false, |word| word.starts_with(SYMBOL_LABEL_USAGE))
+                {
+                    names.push((&(*self.tokens.peek().unwrap().to_string())[1..]).to_string());
+                    self.tokens.next();
+                }
+                if names.is_empty() || self.tokens.peek().is_some() {
+                    return Err("Expect labels for jump table(e.g. .table @foo @bar)");
+                }
+                return Ok(AssemblerInstruction::new(
+                    None,
+                    None,
+                    Some(Directive {
+                        name: directive.to_string(),
+                    }),
+                    Some(LabelUsageList { names }),
+                    None,
+                    None,
+                ));
+            }
             "code" => {
                 return Ok(AssemblerInstruction::new(
                     None,
@@ -401,6 +608,14 @@ impl<'a> InstructionParser<'a> {
             .peek()
             .map_or(false, |word| (*word).to_uppercase() == "HLT".to_string())
         {
+            self.tokens.next();
+            if self
+                .tokens
+                .peek()
+                .map_or(false, |word| word.starts_with(SYMBOL_REGISTER))
+            {
+                return self.parse_one_register_instruction(HLTR);
+            }
             return Ok(AssemblerInstruction::new(
                 Some(Op { opcode: HLT }),
                 None,
@@ -549,6 +764,9 @@ impl<'a> InstructionParser<'a> {
             .map_or(false, |word| (*word).to_uppercase() == "ADD".to_string())
         {
             self.tokens.next();
+            if self.is_immediate_operand_at(1) {
+                return self.parse_register_immediate_register_instruction(ADDI);
+            }
             return self.parse_three_register_instruction(ADD);
         }
 
@@ -558,6 +776,9 @@ impl<'a> InstructionParser<'a> {
             .map_or(false, |word| (*word).to_uppercase() == "SUB".to_string())
         {
             self.tokens.next();
+            if self.is_immediate_operand_at(1) {
+                return self.parse_register_immediate_register_instruction(SUBI);
+            }
             return self.parse_three_register_instruction(SUB);
         }
 
@@ -585,9 +806,80 @@ impl<'a> InstructionParser<'a> {
             .map_or(false, |word| (*word).to_uppercase() == "EQ".to_string())
         {
             self.tokens.next();
+            if self.is_immediate_operand_at(1) {
+                return self.parse_register_immediate_operand_instruction(CMPI);
+            }
             return self.parse_two_register_instruction(EQ);
         }
 
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "ADDI".to_string())
+        {
+            self.tokens.next();
+            return self.parse_register_immediate_register_instruction(ADDI);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "SUBI".to_string())
+        {
+            self.tokens.next();
+            return self.parse_register_immediate_register_instruction(SUBI);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "CMPI".to_string())
+        {
+            self.tokens.next();
+            return self.parse_register_immediate_operand_instruction(CMPI);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "JMPI".to_string())
+        {
+            self.tokens.next();
+            return self.parse_immediate_instruction(JMPI);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "JEI".to_string())
+        {
+            self.tokens.next();
+            return self.parse_immediate_instruction(JEI);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "JNEI".to_string())
+        {
+            self.tokens.next();
+            return self.parse_immediate_instruction(JNEI);
+        }
+
+        if self.tokens.peek().map_or(false, |word| {
+            (*word).to_uppercase() == "LOADLOCAL".to_string()
+        }) {
+            self.tokens.next();
+            return self.parse_register_immediate_operand_instruction(LOADLOCAL);
+        }
+
+        if self.tokens.peek().map_or(false, |word| {
+            (*word).to_uppercase() == "STORELOCAL".to_string()
+        }) {
+            self.tokens.next();
+            return self.parse_register_immediate_operand_instruction(STORELOCAL);
+        }
+
         if self
             .tokens
             .peek()
@@ -624,7 +916,12 @@ impl<'a> InstructionParser<'a> {
             return self.parse_two_register_instruction(GTE);
         }
 
-        // todo : loadf64
+        if self.tokens.peek().map_or(false, |word| {
+            (*word).to_uppercase() == "LOADF64".to_string()
+        }) {
+            self.tokens.next();
+            return self.parse_register_immediate_instruction(LOADF64);
+        }
 
         if self
             .tokens
@@ -671,6 +968,15 @@ impl<'a> InstructionParser<'a> {
             return self.parse_two_register_instruction(EQF64);
         }
 
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "NEQF64".to_string())
+        {
+            self.tokens.next();
+            return self.parse_two_register_instruction(NEQF64);
+        }
+
         if self
             .tokens
             .peek()
@@ -707,12 +1013,76 @@ impl<'a> InstructionParser<'a> {
             return self.parse_two_register_instruction(GTEF64);
         }
 
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "ITOF".to_string())
+        {
+            self.tokens.next();
+            return self.parse_two_register_instruction(ITOF);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "FTOI".to_string())
+        {
+            self.tokens.next();
+            return self.parse_two_register_instruction(FTOI);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "MOVF64".to_string())
+        {
+            self.tokens.next();
+            return self.parse_two_register_instruction(MOVF64);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "NEGF64".to_string())
+        {
+            self.tokens.next();
+            return self.parse_two_register_instruction(NEGF64);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "ABSF64".to_string())
+        {
+            self.tokens.next();
+            return self.parse_two_register_instruction(ABSF64);
+        }
+
+        if self.tokens.peek().map_or(false, |word| {
+            (*word).to_uppercase() == "SQRTF64".to_string()
+        }) {
+            self.tokens.next();
+            return self.parse_two_register_instruction(SQRTF64);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "CMPF64".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(CMPF64);
+        }
+
         if self
             .tokens
             .peek()
             .map_or(false, |word| (*word).to_uppercase() == "JE".to_string())
         {
             self.tokens.next();
+            if self.is_immediate_operand_at(0) {
+                return self.parse_immediate_instruction(JEI);
+            }
             return self.parse_one_register_instruction(JE);
         }
 
@@ -722,6 +1092,9 @@ impl<'a> InstructionParser<'a> {
             .map_or(false, |word| (*word).to_uppercase() == "JNE".to_string())
         {
             self.tokens.next();
+            if self.is_immediate_operand_at(0) {
+                return self.parse_immediate_instruction(JNEI);
+            }
             return self.parse_one_register_instruction(JNE);
         }
 
@@ -767,6 +1140,9 @@ impl<'a> InstructionParser<'a> {
             .map_or(false, |word| (*word).to_uppercase() == "JMP".to_string())
         {
             self.tokens.next();
+            if self.is_immediate_operand_at(0) {
+                return self.parse_immediate_instruction(JMPI);
+            }
             return self.parse_one_register_instruction(JMP);
         }
 
@@ -815,6 +1191,217 @@ impl<'a> InstructionParser<'a> {
             return self.parse_three_register_instruction(XOR);
         }
 
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "SHL".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SHL);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "SHR".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SHR);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "SAR".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SAR);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "MOD".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(MOD);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "DIVU".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(DIVU);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "MODU".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(MODU);
+        }
+
+        if self.tokens.peek().map_or(false, |word| {
+            (*word).to_uppercase() == "LOADREM".to_string()
+        }) {
+            self.tokens.next();
+            return self.parse_one_register_instruction(LOADREM);
+        }
+
+        if self.tokens.peek().map_or(false, |word| {
+            (*word).to_uppercase() == "ADDWRAP".to_string()
+        }) {
+            self.tokens.next();
+            return self.parse_three_register_instruction(ADDWRAP);
+        }
+
+        if self.tokens.peek().map_or(false, |word| {
+            (*word).to_uppercase() == "SUBWRAP".to_string()
+        }) {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SUBWRAP);
+        }
+
+        if self.tokens.peek().map_or(false, |word| {
+            (*word).to_uppercase() == "MULWRAP".to_string()
+        }) {
+            self.tokens.next();
+            return self.parse_three_register_instruction(MULWRAP);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "ADDCHK".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(ADDCHK);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "SUBCHK".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SUBCHK);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "MULCHK".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(MULCHK);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "SEQ".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SEQ);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "SNEQ".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SNEQ);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "SLT".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SLT);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "SLTE".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SLTE);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "SGT".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SGT);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "SGTE".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SGTE);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "SEQF64".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SEQF64);
+        }
+
+        if self.tokens.peek().map_or(false, |word| {
+            (*word).to_uppercase() == "SNEQF64".to_string()
+        }) {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SNEQF64);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "SLTF64".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SLTF64);
+        }
+
+        if self.tokens.peek().map_or(false, |word| {
+            (*word).to_uppercase() == "SLTEF64".to_string()
+        }) {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SLTEF64);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "SGTF64".to_string())
+        {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SGTF64);
+        }
+
+        if self.tokens.peek().map_or(false, |word| {
+            (*word).to_uppercase() == "SGTEF64".to_string()
+        }) {
+            self.tokens.next();
+            return self.parse_three_register_instruction(SGTEF64);
+        }
+
         if self
             .tokens
             .peek()
@@ -848,9 +1435,34 @@ impl<'a> InstructionParser<'a> {
             .map_or(false, |word| (*word).to_uppercase() == "CALL".to_string())
         {
             self.tokens.next();
+            if self.is_immediate_operand_at(0) {
+                return self.parse_immediate_instruction(CALLI);
+            }
             return self.parse_one_register_instruction(CALL);
         }
 
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "CALLI".to_string())
+        {
+            self.tokens.next();
+            return self.parse_immediate_instruction(CALLI);
+        }
+
+        if self.tokens.peek().map_or(false, |word| {
+            (*word).to_uppercase() == "SYSCALL".to_string()
+        }) {
+            self.tokens.next();
+            let instruction = self.parse_immediate_instruction(SYSCALL)?;
+            if let Some(IntegerOperand { value }) = instruction.operand1 {
+                if value > 65535 {
+                    return Err("An Unsigned Integer is expected(e.g. 0...65535)");
+                }
+            }
+            return Ok(instruction);
+        }
+
         if self
             .tokens
             .peek()
@@ -875,6 +1487,87 @@ impl<'a> InstructionParser<'a> {
             return self.parse_one_register_instruction(PRTS);
         }
 
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "ALOC".to_string())
+        {
+            self.tokens.next();
+            return self.parse_two_register_instruction(ALOC);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "FREE".to_string())
+        {
+            self.tokens.next();
+            return self.parse_one_register_instruction(FREE);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "LDB".to_string())
+        {
+            self.tokens.next();
+            return self.parse_two_register_immediate_operand_instruction(LDB);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "LDW".to_string())
+        {
+            self.tokens.next();
+            return self.parse_two_register_immediate_operand_instruction(LDW);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "LDROW".to_string())
+        {
+            self.tokens.next();
+            return self.parse_two_register_immediate_operand_instruction(LDROW);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "LDF64".to_string())
+        {
+            self.tokens.next();
+            return self.parse_two_register_immediate_operand_instruction(LDF64);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "STB".to_string())
+        {
+            self.tokens.next();
+            return self.parse_two_register_immediate_operand_instruction(STB);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "STW".to_string())
+        {
+            self.tokens.next();
+            return self.parse_two_register_immediate_operand_instruction(STW);
+        }
+
+        if self
+            .tokens
+            .peek()
+            .map_or(false, |word| (*word).to_uppercase() == "STF64".to_string())
+        {
+            self.tokens.next();
+            return self.parse_two_register_immediate_operand_instruction(STF64);
+        }
+
         return Err("Unexpected Assembly Code.");
     }
 }
@@ -4,6 +4,8 @@ pub mod assembler_instruction;
 pub mod assembler_phase;
 pub mod assembler_section;
 pub mod assembly_parser;
+pub mod debug_info;
+pub mod disassembler;
 pub mod elf;
 pub mod instructions_parser;
 pub mod symbol_table;
@@ -1,6 +1,8 @@
 #[derive(Debug, PartialEq)]
 pub enum SymbolType {
     Label,
+    /* a label in the .code section, its offset is a program address */
+    CodeLabel,
 }
 
 #[derive(Debug, PartialEq)]
@@ -30,6 +32,18 @@ impl Symbol {
             symbol_type,
         }
     }
+
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    pub fn offset(&self) -> u32 {
+        return self.offset;
+    }
+
+    pub fn symbol_type(&self) -> &SymbolType {
+        return &self.symbol_type;
+    }
 }
 
 impl SymbolTable {
@@ -68,4 +82,37 @@ impl SymbolTable {
             }
         }
     }
+
+    pub fn set_symbol_type(&mut self, s: &str, symbol_type: SymbolType) {
+        for symbol in &mut self.symbols {
+            if symbol.name == s.to_string() {
+                symbol.symbol_type = symbol_type;
+                return;
+            }
+        }
+    }
+
+    /* names and program offsets of the .code labels, sorted by offset */
+    pub fn code_labels(&self) -> Vec<(String, usize)> {
+        let mut labels: Vec<(String, usize)> = self
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.symbol_type == SymbolType::CodeLabel)
+            .map(|symbol| (symbol.name.clone(), symbol.offset as usize))
+            .collect();
+        labels.sort_by_key(|label| label.1);
+        return labels;
+    }
+
+    /* names and ro_data offsets of the .data labels, sorted by offset */
+    pub fn data_labels(&self) -> Vec<(String, usize)> {
+        let mut labels: Vec<(String, usize)> = self
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.symbol_type == SymbolType::Label)
+            .map(|symbol| (symbol.name.clone(), symbol.offset as usize))
+            .collect();
+        labels.sort_by_key(|label| label.1);
+        return labels;
+    }
 }
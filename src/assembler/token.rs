@@ -9,4 +9,5 @@ pub enum Token {
     LabelUsage { name: String },
     Directive { name: String },
     IrString { name: String },
+    LabelUsageList { names: Vec<String> },
 }
@@ -1,10 +1,88 @@
+use std::{env, fs, process};
+use Dulang::assembler::assembler::Assembler;
+use Dulang::assembler::elf::ELF_HEADER_PREFIX;
 use Dulang::repl::repl::REPL;
 use Dulang::repl::terminal::terminal::Terminal;
+use Dulang::vm::vm::VM;
+use Dulang::vm::vm_state::VmState::Halted;
 
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() > 3 && args[2] == "-o" {
+        let debug_info = args.len() > 4 && args[4] == "-g";
+        process::exit(assemble_file(&args[1], &args[3], debug_info));
+    }
+    if args.len() > 1 {
+        process::exit(run_file(&args[1]));
+    }
+
     let mut repl = REPL::new();
     repl.run();
 
     //    let term = Terminal::new();
     //        print!("T{}", term.read_line().unwrap());
 }
+
+fn assemble(filename: &str, debug_info: bool) -> Option<Vec<u8>> {
+    let source = match fs::read(filename) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Asm load failed: {}", e);
+            return None;
+        }
+    };
+    if source.starts_with(&ELF_HEADER_PREFIX) {
+        return Some(source);
+    }
+    let mut assembler = Assembler::new();
+    if debug_info {
+        assembler.enable_debug_info(filename);
+    }
+    match assembler.process(&String::from_utf8_lossy(&source)) {
+        Ok(program) => Some(program),
+        Err(errors) => {
+            for e in errors {
+                eprintln!("Error in assemble : {:?}", e);
+            }
+            None
+        }
+    }
+}
+
+/* `du prog.asm -o prog.delf [-g]` writes the assembled DELF file, -g adds a debug section */
+fn assemble_file(filename: &str, output: &str, debug_info: bool) -> i32 {
+    let program = match assemble(filename, debug_info) {
+        Some(program) => program,
+        None => return 1,
+    };
+    match fs::write(output, program) {
+        Ok(_) => 0,
+        Err(e) => {
+            eprintln!("Elf output failed: {}", e);
+            1
+        }
+    }
+}
+
+/* run an assembly or DELF file, its exit status becomes the process exit code */
+fn run_file(filename: &str) -> i32 {
+    let program = match assemble(filename, true) {
+        Some(program) => program,
+        None => return 1,
+    };
+
+    let mut vm = VM::new();
+    vm.set_exit_banner(false);
+    if let Err(e) = vm.load_program(program) {
+        eprintln!("{}", e);
+        return 1;
+    }
+    match vm.run() {
+        Ok(Halted(status)) => status,
+        Ok(_) => 0,
+        Err(e) => {
+            eprintln!("{}, in {}", e, vm.location(e.pc()));
+            1
+        }
+    }
+}
@@ -13,8 +13,8 @@ mod tests {
             repl.vm.program,
             vec![
                 0x01, 0x00, 0x00, 0x00, 0x01, 0x01, 0x00, 0x32, 0x01, 0x02, 0x00, 0x00, 0x09, 0x00,
-                0x01, 0x01, 0x1F, 0x00, 0x00, 0x0E, 0x1F, 0x0D, 0x01, 0x0C, 0x02, 0x01, 0x1F, 0x00,
-                0x0C, 0x0F, 0x1F, 0x01, 0x1F, 0x00, 0x0F, 0x0E, 0x1F, 0x00
+                0x01, 0x01, 0x1F, 0x00, 0x00, 0x0E, 0x1F, 0x0D, 0x01, 0x0C, 0x02, 0x56, 0x00, 0x00,
+                0x00, 0x0C, 0x01, 0x1F, 0x00, 0x0F, 0x0E, 0x1F, 0x00,
             ]
         );
 
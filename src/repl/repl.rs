@@ -1,8 +1,16 @@
 use crate::assembler::assembler::Assembler;
+use crate::assembler::disassembler::Disassembler;
+use crate::assembler::elf::ELF_HEADER_PREFIX;
 use crate::assembler::instructions_parser::InstructionParser;
 use crate::repl::repl::ReplMode::Assembly;
-use crate::repl::terminal::color_print::ColorPrint;
+use crate::repl::terminal::color_print::{ColorOutput, ColorPrint};
+use crate::vm::gc::DEFAULT_GC_THRESHOLD;
+use crate::vm::overflow_mode::OverflowMode::{Saturating, Trapping, Wrapping};
+use crate::vm::trace::TraceSink::Writer;
+use crate::vm::trace::DEFAULT_TRACE_LIMIT;
 use crate::vm::vm::VM;
+use crate::vm::vm_error::VmError;
+use crate::vm::vm_state::VmState::Halted;
 use std::fs;
 use std::io::Write;
 use std::num::ParseIntError;
@@ -21,9 +29,11 @@ pub enum ReplMode {
 
 impl REPL {
     pub fn new() -> REPL {
+        let mut vm = VM::new();
+        vm.set_output(Box::new(ColorOutput::cyan()));
         REPL {
             command_buffer: Vec::new(),
-            vm: VM::new(),
+            vm,
             mode: Assembly,
         }
     }
@@ -44,19 +54,20 @@ impl REPL {
         Ok(results)
     }
 
-    pub fn run_asm_file(&mut self, filename: &str) {
+    /* returns the exit status of the program, 1 when it can not be assembled or faults */
+    pub fn run_asm_file(&mut self, filename: &str) -> i32 {
         let src = fs::read_to_string(filename);
         match src {
             Ok(asm_src) => {
                 ColorPrint::println_light_blue(format!("{}\n", asm_src).as_str());
 
                 let mut assembler = Assembler::new();
+                assembler.enable_debug_info(filename);
                 let instructions = assembler.process(&asm_src);
                 match instructions {
                     Ok(ins) => {
-                        self.vm.load_program(ins);
-                        self.vm.set_ro_data(assembler.ro_section);
-                        self.vm.run();
+                        self.vm.set_labels(assembler.symbol_table.code_labels());
+                        self.run_program(ins)
                     }
                     Err(errs) => {
                         for e in errs {
@@ -64,11 +75,67 @@ impl REPL {
                                 format!("Error in assemble : {:#?}", e).as_str(),
                             );
                         }
+                        1
                     }
                 }
             }
             Err(e) => {
                 ColorPrint::println_light_red(format!("Asm load failed: {:#?}", e).as_str());
+                1
+            }
+        }
+    }
+
+    /* returns the exit status of the program, 1 when it is not a valid DELF file or faults */
+    pub fn run_elf_file(&mut self, filename: &str) -> i32 {
+        match fs::read(filename) {
+            Ok(program) => {
+                self.vm.set_labels(Vec::new());
+                self.run_program(program)
+            }
+            Err(e) => {
+                ColorPrint::println_light_red(format!("Elf load failed: {:#?}", e).as_str());
+                1
+            }
+        }
+    }
+
+    /* labels and source lines come from the debug section, or the vm labels for the current program */
+    fn disassembler(&self, filename: Option<&&str>) -> Result<Disassembler, String> {
+        let filename = match filename {
+            Some(filename) => filename,
+            None => {
+                let mut disassembler =
+                    Disassembler::from_delf(&self.vm.to_delf()).map_err(|e| e.to_string())?;
+                if self.vm.debug_info().is_none() {
+                    disassembler.set_labels(self.vm.labels().clone(), Vec::new());
+                }
+                return Ok(disassembler);
+            }
+        };
+        let source = fs::read(filename).map_err(|e| format!("{}", e))?;
+        if source.starts_with(&ELF_HEADER_PREFIX) {
+            return Disassembler::from_delf(&source).map_err(|e| e.to_string());
+        }
+        let mut assembler = Assembler::new();
+        assembler.enable_debug_info(filename);
+        let program = assembler
+            .process(&String::from_utf8_lossy(&source))
+            .map_err(|errors| format!("Error in assemble : {:?}", errors))?;
+        return Disassembler::from_delf(&program).map_err(|e| e.to_string());
+    }
+
+    fn run_program(&mut self, program: Vec<u8>) -> i32 {
+        if let Err(e) = self.vm.load_program(program) {
+            ColorPrint::println_light_red(format!("[ELF ERROR]: {}", e).as_str());
+            return 1;
+        }
+        match self.vm.run() {
+            Ok(Halted(status)) => status,
+            Ok(_) => 0,
+            Err(e) => {
+                REPL::print_vm_error(&e, &self.vm);
+                1
             }
         }
     }
@@ -103,9 +170,30 @@ impl REPL {
                         ),
                     }
                 } else if commands.peek().map_or(false, |w| (*w == ".load_elf")) {
-                    // todo : load elf file to execute.
+                    commands.next();
+                    match commands.peek() {
+                        Some(filepath) => {
+                            self.run_elf_file(filepath);
+                        }
+                        None => ColorPrint::println_light_red(
+                            "No input: need a file path for elf file.",
+                        ),
+                    }
                 } else if commands.peek().map_or(false, |w| (*w == ".output_elf")) {
-                    // todo : output elf file.
+                    commands.next();
+                    match commands.peek() {
+                        Some(filepath) => match fs::write(filepath, self.vm.to_delf()) {
+                            Ok(_) => ColorPrint::println_light_purple(
+                                format!("Program written to {}.", filepath).as_str(),
+                            ),
+                            Err(e) => ColorPrint::println_light_red(
+                                format!("Elf output failed: {:#?}", e).as_str(),
+                            ),
+                        },
+                        None => ColorPrint::println_light_red(
+                            "No input: need a file path for elf file.",
+                        ),
+                    }
                 } else if commands.peek().map_or(false, |w| (*w == ".mode")) {
                     commands.next();
                     match commands.peek() {
@@ -137,6 +225,16 @@ impl REPL {
                     }
                     ColorPrint::println_light_green("");
                     ColorPrint::println_light_green("End of Program Listing.")
+                } else if commands.peek().map_or(false, |w| *w == ".disassemble") {
+                    commands.next();
+                    match self.disassembler(commands.peek()) {
+                        Ok(disassembler) => {
+                            ColorPrint::println_light_blue(format!("{}", disassembler).as_str())
+                        }
+                        Err(e) => ColorPrint::println_light_red(
+                            format!("Disassemble failed: {}", e).as_str(),
+                        ),
+                    }
                 } else if commands.peek().map_or(false, |w| (*w == ".clear")) {
                     ColorPrint::println_light_green("Clearing in VM's program vector:");
                     let len = self.vm.program.len();
@@ -168,16 +266,192 @@ impl REPL {
                         format!("R0-R31 {:?}", self.vm.registers).as_str(),
                     );
                     ColorPrint::println_light_green("End of Registers Listing.")
+                } else if commands.peek().map_or(false, |w| *w == ".gc") {
+                    commands.next();
+                    match commands.peek() {
+                        Some(&"on") => match self.vm.enable_gc(DEFAULT_GC_THRESHOLD) {
+                            Ok(_) => ColorPrint::println_light_purple("GC mode on."),
+                            Err(e) => ColorPrint::println_light_red(e),
+                        },
+                        Some(_) => ColorPrint::println_light_red("Expect: .gc / .gc on"),
+                        None => {
+                            if !self.vm.gc.is_enabled() {
+                                ColorPrint::println_light_red("GC mode is off, use .gc on");
+                            } else {
+                                let stats = self.vm.collect_garbage();
+                                ColorPrint::println_light_green("Garbage collected:");
+                                ColorPrint::println_light_purple(format!("{}", stats).as_str());
+                            }
+                        }
+                    }
+                } else if commands.peek().map_or(false, |w| *w == ".overflow") {
+                    commands.next();
+                    match commands.peek() {
+                        Some(&"wrap") => self.vm.set_overflow_mode(Wrapping),
+                        Some(&"saturate") => self.vm.set_overflow_mode(Saturating),
+                        Some(&"trap") => self.vm.set_overflow_mode(Trapping),
+                        Some(_) => {
+                            ColorPrint::println_light_red("Expect: .overflow wrap|saturate|trap")
+                        }
+                        None => {}
+                    }
+                    ColorPrint::println_light_purple(
+                        format!("Overflow mode: {:?}", self.vm.overflow_mode()).as_str(),
+                    );
+                } else if commands.peek().map_or(false, |w| *w == ".trace") {
+                    commands.next();
+                    match commands.next() {
+                        Some("on") => match commands.next() {
+                            Some(path) => match fs::File::create(path) {
+                                Ok(file) => {
+                                    self.vm.enable_trace(
+                                        Writer(Box::new(file)),
+                                        Some(DEFAULT_TRACE_LIMIT),
+                                    );
+                                    ColorPrint::println_light_purple(
+                                        format!("Trace on, writing to {}.", path).as_str(),
+                                    );
+                                }
+                                Err(e) => ColorPrint::println_light_red(
+                                    format!("Trace file create failed: {:#?}", e).as_str(),
+                                ),
+                            },
+                            None => {
+                                self.vm.enable_trace(
+                                    Writer(Box::new(std::io::stdout())),
+                                    Some(DEFAULT_TRACE_LIMIT),
+                                );
+                                ColorPrint::println_light_purple("Trace on.");
+                            }
+                        },
+                        Some("off") => {
+                            self.vm.disable_trace();
+                            ColorPrint::println_light_purple("Trace off.");
+                        }
+                        _ => ColorPrint::println_light_red("Expect: .trace on [file]|off"),
+                    }
+                } else if commands.peek().map_or(false, |w| *w == ".profile") {
+                    commands.next();
+                    match commands.next() {
+                        Some("on") => {
+                            self.vm.enable_profiler(true);
+                            ColorPrint::println_light_purple("Profiler on.");
+                        }
+                        Some("off") => {
+                            self.vm.disable_profiler();
+                            ColorPrint::println_light_purple("Profiler off.");
+                        }
+                        Some(_) => ColorPrint::println_light_red("Expect: .profile [on|off]"),
+                        None => match self.vm.profile_report() {
+                            Some(report) => {
+                                ColorPrint::println_light_green("Profile of the last runs:");
+                                for line in report.to_string().lines() {
+                                    ColorPrint::println_light_blue(line);
+                                }
+                            }
+                            None => ColorPrint::println_light_red(
+                                "Profiler is off, enable it with .profile on",
+                            ),
+                        },
+                    }
+                } else if commands.peek().map_or(false, |w| *w == ".save_state") {
+                    commands.next();
+                    match commands.peek() {
+                        Some(path) => match fs::write(path, self.vm.snapshot()) {
+                            Ok(_) => ColorPrint::println_light_purple(
+                                format!("VM state saved to {}.", path).as_str(),
+                            ),
+                            Err(e) => ColorPrint::println_light_red(
+                                format!("Save state failed: {:#?}", e).as_str(),
+                            ),
+                        },
+                        None => ColorPrint::println_light_red(
+                            "No input: need a file path for the vm state.",
+                        ),
+                    }
+                } else if commands.peek().map_or(false, |w| *w == ".load_state") {
+                    commands.next();
+                    match commands.peek() {
+                        Some(path) => match fs::read(path) {
+                            Ok(bytes) => match self.vm.restore(&bytes) {
+                                Ok(_) => ColorPrint::println_light_purple(
+                                    format!("VM state loaded from {}.", path).as_str(),
+                                ),
+                                Err(e) => ColorPrint::println_light_red(
+                                    format!("Load state failed: {}", e).as_str(),
+                                ),
+                            },
+                            Err(e) => ColorPrint::println_light_red(
+                                format!("Load state failed: {:#?}", e).as_str(),
+                            ),
+                        },
+                        None => ColorPrint::println_light_red(
+                            "No input: need a file path for the vm state.",
+                        ),
+                    }
+                } else if commands.peek().map_or(false, |w| *w == ".heap") {
+                    ColorPrint::println_light_green("Listing heap usage:");
+                    let stats = self.vm.heap_stats();
+                    ColorPrint::println_light_purple(
+                        format!("Size: {} bytes", stats.heap_size).as_str(),
+                    );
+                    ColorPrint::println_light_purple(
+                        format!(
+                            "Used: {} bytes in {} blocks",
+                            stats.used_bytes, stats.used_blocks
+                        )
+                        .as_str(),
+                    );
+                    ColorPrint::println_light_purple(
+                        format!(
+                            "Free: {} bytes in {} blocks",
+                            stats.free_bytes, stats.free_blocks
+                        )
+                        .as_str(),
+                    );
+                    ColorPrint::println_light_purple(
+                        format!("Allocations: {}, Frees: {}", stats.allocations, stats.frees)
+                            .as_str(),
+                    );
+                    ColorPrint::println_light_green("End of Heap Listing.")
                 } else if commands.peek().map_or(false, |w| (*w == ".help")) {
                     ColorPrint::println_light_green("Command Usage:");
                     ColorPrint::println_light_blue(
                         "  .load_asm   : Load asm file and run. e.g. .load_asm xxx.asm",
                     );
+                    ColorPrint::println_light_blue(
+                        "  .load_elf   : Load delf file and run. e.g. .load_elf xxx.delf",
+                    );
+                    ColorPrint::println_light_blue(
+                        "  .output_elf : Write program in current vm as delf file. e.g. .output_elf xxx.delf",
+                    );
                     ColorPrint::println_light_blue("  .history    : Command history");
                     ColorPrint::println_light_blue(
                         "  .registers  : Registers and content in current vm",
                     );
                     ColorPrint::println_light_blue("  .program    : Program in current vm");
+                    ColorPrint::println_light_blue(
+                        "  .disassemble: Disassemble current program, or a file. e.g. .disassemble xxx.delf",
+                    );
+                    ColorPrint::println_light_blue("  .heap       : Heap usage in current vm");
+                    ColorPrint::println_light_blue(
+                        "  .gc         : Collect garbage and print stats, .gc on to enable GC mode",
+                    );
+                    ColorPrint::println_light_blue(
+                        "  .overflow   : Integer overflow mode, e.g. .overflow wrap|saturate|trap",
+                    );
+                    ColorPrint::println_light_blue(
+                        "  .trace      : Trace executed instructions, e.g. .trace on [file]|off",
+                    );
+                    ColorPrint::println_light_blue(
+                        "  .profile    : Print the execution profile, .profile on|off to switch it",
+                    );
+                    ColorPrint::println_light_blue(
+                        "  .save_state : Save the vm state to a file, e.g. .save_state vm.state",
+                    );
+                    ColorPrint::println_light_blue(
+                        "  .load_state : Resume the vm state from a file, e.g. .load_state vm.state",
+                    );
                     ColorPrint::println_light_blue("  .clear      : Clear vm program memory");
                     ColorPrint::println_light_blue("  .reset      : Reset vm");
                     ColorPrint::println_light_blue(
@@ -195,7 +469,9 @@ impl REPL {
                                             self.vm.program.push(byte);
                                         }
                                     }
-                                    self.vm.run_once();
+                                    if let Err(e) = self.vm.run_once() {
+                                        REPL::print_vm_error(&e, &self.vm);
+                                    }
                                 }
                                 Err(e) => {
                                     ColorPrint::println_light_red(
@@ -211,7 +487,9 @@ impl REPL {
                                     for byte in ins {
                                         self.vm.program.push(*byte);
                                     }
-                                    self.vm.run_once();
+                                    if let Err(e) = self.vm.run_once() {
+                                        REPL::print_vm_error(&e, &self.vm);
+                                    }
                                 }
                                 Err(e) => {
                                     ColorPrint::println_light_red(
@@ -226,6 +504,12 @@ impl REPL {
         }
     }
 
+    fn print_vm_error(error: &VmError, vm: &VM) {
+        ColorPrint::println_light_red(
+            format!("[VM ERROR]: {}, in {}", error, vm.location(error.pc())).as_str(),
+        );
+    }
+
     fn printSplash() {
         ColorPrint::println_light_blue("");
         ColorPrint::println_light_blue("                   |   Du.rs release_0.1.0");
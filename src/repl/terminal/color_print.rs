@@ -1,8 +1,38 @@
 /*
  * Copyright (c) 2019. NeroYang
  */
+use std::io;
+use std::io::{stdout, Write};
+
 pub struct ColorPrint {}
 
+/* Write sink for the vm output, every write is wrapped in the color escape codes */
+pub struct ColorOutput {
+    color: &'static str,
+}
+
+impl ColorOutput {
+    pub fn cyan() -> ColorOutput {
+        ColorOutput {
+            color: "\x1b[0;36m",
+        }
+    }
+}
+
+impl Write for ColorOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut out = stdout();
+        out.write_all(self.color.as_bytes())?;
+        out.write_all(buf)?;
+        out.write_all(b"\x1b[0m")?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        stdout().flush()
+    }
+}
+
 impl ColorPrint {
     fn println_black(msg: &str) {
         println!("\x1b[30m {} \x1b[0m", msg);
@@ -0,0 +1,47 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+#[cfg(test)]
+pub mod fixtures {
+    use crate::assembler::assembler::Assembler;
+    use crate::vm::vm::VM;
+
+    /* recursive factorial of `n`, the result is left in $0 */
+    pub fn factorial_source(n: i32) -> String {
+        return format!(
+            ".code\n\
+             main: load $1 #{}\n\
+             push $1\n\
+             call @fact\n\
+             pop $1\n\
+             hlt\n\
+             fact: loadlocal $1 #-3\n\
+             eq $1 #1\n\
+             jne @recurse\n\
+             load $0 #1\n\
+             ret\n\
+             recurse: push $1\n\
+             sub $1 #1 $2\n\
+             push $2\n\
+             call @fact\n\
+             pop $2\n\
+             loadlocal $1 #0\n\
+             mul $0 $1 $0\n\
+             ret\n\
+             .data",
+            n
+        );
+    }
+
+    /* a VM with the factorial program loaded and its code labels set, not started */
+    pub fn factorial_vm(n: i32) -> VM {
+        let mut assembler = Assembler::new();
+        let program = assembler.process(&factorial_source(n)).unwrap();
+        let mut vm = VM::new();
+        vm.set_exit_banner(false);
+        vm.load_program(program).unwrap();
+        vm.set_labels(assembler.symbol_table.code_labels());
+        return vm;
+    }
+}
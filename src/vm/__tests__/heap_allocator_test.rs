@@ -0,0 +1,98 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+#[cfg(test)]
+mod tests {
+    use crate::vm::heap_allocator::{HeapAllocator, HeapBlock};
+
+    #[test]
+    fn should_allocate_aligned_blocks_at_end_of_heap() {
+        let mut heap: Vec<u8> = Vec::new();
+        let mut allocator = HeapAllocator::new();
+        assert_eq!(allocator.allocate(&mut heap, 3), 0);
+        assert_eq!(allocator.allocate(&mut heap, 9), 8);
+        assert_eq!(allocator.allocate(&mut heap, 0), 24);
+        assert_eq!(heap.len(), 32);
+    }
+
+    #[test]
+    fn should_split_free_block_when_reused() {
+        let mut heap: Vec<u8> = Vec::new();
+        let mut allocator = HeapAllocator::new();
+        let first = allocator.allocate(&mut heap, 32);
+        allocator.allocate(&mut heap, 8);
+        assert!(allocator.free(first));
+        assert_eq!(allocator.allocate(&mut heap, 8), 0);
+        assert_eq!(
+            allocator.blocks(),
+            &vec![
+                HeapBlock {
+                    offset: 0,
+                    size: 8,
+                    free: false
+                },
+                HeapBlock {
+                    offset: 8,
+                    size: 24,
+                    free: true
+                },
+                HeapBlock {
+                    offset: 32,
+                    size: 8,
+                    free: false
+                },
+            ]
+        );
+        assert_eq!(heap.len(), 40);
+    }
+
+    #[test]
+    fn should_coalesce_neighbour_free_blocks() {
+        let mut heap: Vec<u8> = Vec::new();
+        let mut allocator = HeapAllocator::new();
+        let first = allocator.allocate(&mut heap, 8);
+        let second = allocator.allocate(&mut heap, 8);
+        let third = allocator.allocate(&mut heap, 8);
+        assert!(allocator.free(first));
+        assert!(allocator.free(third));
+        assert!(allocator.free(second));
+        assert_eq!(
+            allocator.blocks(),
+            &vec![HeapBlock {
+                offset: 0,
+                size: 24,
+                free: true
+            }]
+        );
+        assert_eq!(allocator.allocate(&mut heap, 24), 0);
+        assert_eq!(heap.len(), 24);
+    }
+
+    #[test]
+    fn should_not_free_unknown_block() {
+        let mut heap: Vec<u8> = Vec::new();
+        let mut allocator = HeapAllocator::new();
+        allocator.allocate(&mut heap, 16);
+        assert!(!allocator.free(8));
+        assert!(allocator.free(0));
+        assert!(!allocator.free(0));
+    }
+
+    #[test]
+    fn should_report_stats() {
+        let mut heap: Vec<u8> = Vec::new();
+        let mut allocator = HeapAllocator::new();
+        let first = allocator.allocate(&mut heap, 16);
+        allocator.allocate(&mut heap, 8);
+        allocator.free(first);
+        let stats = allocator.stats(heap.len());
+        assert_eq!(stats.heap_size, 24);
+        assert_eq!(stats.used_bytes, 8);
+        assert_eq!(stats.used_blocks, 1);
+        assert_eq!(stats.free_bytes, 16);
+        assert_eq!(stats.free_blocks, 1);
+        assert_eq!(stats.allocations, 2);
+        assert_eq!(stats.frees, 1);
+    }
+}
@@ -5,7 +5,9 @@
 #[cfg(test)]
 mod tests {
     use crate::vm::instruction::Instruction;
-    use crate::vm::instruction::OpCode::HLT;
+    use crate::vm::instruction::OpCode;
+    use crate::vm::instruction::OpCode::{ADDI, CALLI, HLT, LOAD, LOAD32, RET, SYSCALL};
+    use crate::vm::instruction::OperandKind::{Immediate16, Immediate32, Register};
 
     #[test]
     fn should_create_opcode() {
@@ -18,4 +20,27 @@ mod tests {
         let instruction = Instruction::new(HLT);
         assert_eq!(instruction.opcode, HLT);
     }
+
+    #[test]
+    fn should_return_operand_length() {
+        assert_eq!(HLT.operand_length(), 0);
+        assert_eq!(RET.operand_length(), 0);
+        assert_eq!(LOAD.operand_length(), 3);
+        assert_eq!(SYSCALL.operand_length(), 2);
+        assert_eq!(CALLI.operand_length(), 4);
+        assert_eq!(LOAD32.operand_length(), 5);
+        assert_eq!(OpCode::from(254).operand_length(), 0);
+    }
+
+    #[test]
+    fn should_describe_operands() {
+        assert_eq!(ADDI.operands(), &[Register, Immediate16, Register]);
+        assert_eq!(LOAD32.operands(), &[Register, Immediate32]);
+        assert_eq!(HLT.operands(), &[]);
+        for byte in 0..=255 {
+            let opcode = OpCode::from(byte);
+            let length: usize = opcode.operands().iter().map(|kind| kind.length()).sum();
+            assert_eq!(length, opcode.operand_length());
+        }
+    }
 }
@@ -2,5 +2,11 @@
  * Copyright (c) 2019. NeroYang
  */
 
+pub mod common;
+pub mod heap_allocator_test;
 pub mod instruction_test;
+pub mod profiler_test;
+pub mod snapshot_test;
+pub mod trace_test;
+pub mod verifier_test;
 pub mod vm_test;
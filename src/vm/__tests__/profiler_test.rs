@@ -0,0 +1,77 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+#[cfg(test)]
+mod tests {
+    use crate::vm::__tests__::common::fixtures::factorial_vm;
+    use crate::vm::instruction::OpCode::{CALLI, HLT, LOAD};
+    use crate::vm::profiler::Profiler;
+    use crate::vm::vm::VM;
+    use crate::vm::vm_io::SharedBuffer;
+
+    fn profile(output: &SharedBuffer) -> VM {
+        let mut vm = factorial_vm(5);
+        vm.set_output(Box::new(output.clone()));
+        vm.enable_profiler(true);
+        vm.run().unwrap();
+        return vm;
+    }
+
+    #[test]
+    fn should_count_instructions_per_label() {
+        let output = SharedBuffer::new();
+        let vm = profile(&output);
+        assert_eq!(vm.registers[0], 120);
+
+        let report = vm.profile_report().unwrap();
+        assert_eq!(report.total, 54);
+        assert_eq!(
+            report.labels,
+            vec![
+                ("recurse".to_string(), 32),
+                ("fact".to_string(), 17),
+                ("main".to_string(), 5)
+            ]
+        );
+        assert_eq!(report.calls, vec![("fact".to_string(), 5)]);
+        assert_eq!(
+            report
+                .opcodes
+                .iter()
+                .find(|(op, _)| *op == CALLI)
+                .unwrap()
+                .1,
+            5
+        );
+    }
+
+    #[test]
+    fn should_write_report_at_halt() {
+        let output = SharedBuffer::new();
+        profile(&output);
+        let text = output.text();
+        assert!(text.starts_with("instructions executed: 54\nhot labels:\n  recurse"));
+        assert!(text.contains("calls:\n  fact"));
+    }
+
+    #[test]
+    fn should_count_opcodes_and_pcs() {
+        let mut profiler = Profiler::new();
+        profiler.record_instruction(0, LOAD as u8);
+        profiler.record_instruction(4, LOAD as u8);
+        profiler.record_instruction(4, LOAD as u8);
+        profiler.record_instruction(8, HLT as u8);
+        profiler.record_call(4);
+        assert_eq!(profiler.total(), 4);
+        assert_eq!(profiler.pc_count(4), 2);
+        assert_eq!(profiler.pc_count(100), 0);
+        assert_eq!(profiler.opcode_count(LOAD), 3);
+        assert_eq!(profiler.call_count(4), 1);
+
+        let report = profiler.report(&[]);
+        assert_eq!(report.labels, vec![("<no label>".to_string(), 4)]);
+        assert_eq!(report.opcodes, vec![(LOAD, 3), (HLT, 1)]);
+        assert_eq!(report.calls, vec![("4".to_string(), 1)]);
+    }
+}
@@ -0,0 +1,145 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assembler::Assembler;
+    use crate::vm::__tests__::common::fixtures::{factorial_source, factorial_vm};
+    use crate::vm::heap_allocator::{HeapAllocator, HeapBlock};
+    use crate::vm::overflow_mode::OverflowMode::Saturating;
+    use crate::vm::snapshot::SnapshotError::{
+        BadMagic, InvalidValue, Truncated, UnsupportedVersion,
+    };
+    use crate::vm::vm::VM;
+    use crate::vm::vm_state::VmState::{Halted, Running};
+
+    #[test]
+    fn should_resume_from_snapshot() {
+        let mut vm = factorial_vm(6);
+        vm.set_overflow_mode(Saturating);
+        vm.heap = vec![1, 2, 3];
+        assert_eq!(vm.run_for(20), Ok(Running));
+        let snapshot = vm.snapshot();
+
+        let mut resumed = VM::new();
+        resumed.set_exit_banner(false);
+        resumed.restore(&snapshot).unwrap();
+        assert_eq!(resumed.snapshot(), snapshot);
+        assert_eq!(resumed.overflow_mode(), Saturating);
+        assert_eq!(resumed.call_depth(), vm.call_depth());
+
+        assert_eq!(vm.run(), Ok(Halted(0)));
+        assert_eq!(resumed.run(), Ok(Halted(0)));
+        assert_eq!(resumed.registers[0], 720);
+        assert_eq!(resumed.snapshot(), vm.snapshot());
+    }
+
+    #[test]
+    fn should_keep_heap_blocks_in_snapshot() {
+        let mut vm = factorial_vm(6);
+        vm.registers[5] = 16;
+        vm.program = vec![11, 5, 6, 0]; /*ALOC $5 $6; HLT*/
+        vm.run().unwrap();
+
+        let mut resumed = VM::new();
+        resumed.restore(&vm.snapshot()).unwrap();
+        assert_eq!(resumed.heap_stats(), vm.heap_stats());
+        assert_eq!(resumed.heap.len(), 16);
+    }
+
+    #[test]
+    fn should_reject_bad_snapshot() {
+        let vm = factorial_vm(6);
+        let snapshot = vm.snapshot();
+        let mut target = VM::new();
+
+        assert_eq!(target.restore(&[1, 2, 3, 4, 0, 1]), Err(BadMagic));
+
+        let mut future = snapshot.clone();
+        future[5] = 3;
+        assert_eq!(
+            target.restore(&future),
+            Err(UnsupportedVersion { version: 3 })
+        );
+
+        let truncated = &snapshot[..snapshot.len() - 1];
+        assert_eq!(
+            target.restore(truncated),
+            Err(Truncated {
+                offset: snapshot.len() - 1
+            })
+        );
+        assert_eq!(target.program.len(), 0);
+        assert_eq!(target.snapshot(), VM::new().snapshot());
+    }
+
+    fn debug_vm(source: &str) -> VM {
+        let mut assembler = Assembler::new();
+        assembler.enable_debug_info("factorial.asm");
+        let mut vm = VM::new();
+        vm.set_exit_banner(false);
+        vm.load_program(assembler.process(source).unwrap()).unwrap();
+        return vm;
+    }
+
+    #[test]
+    fn should_restore_entry_and_debug_info_into_another_vm() {
+        let mut vm = debug_vm(&factorial_source(6));
+        assert_eq!(vm.run_for(20), Ok(Running));
+
+        let mut target = debug_vm(".code\nhlt\nother: hlt\n.data");
+        target.restore(&vm.snapshot()).unwrap();
+        assert_eq!(target.entry, vm.entry);
+        assert_eq!(target.labels(), vm.labels());
+        assert_eq!(target.location(target.pc), vm.location(vm.pc));
+        assert!(target.location(target.pc).contains("(factorial.asm:"));
+
+        let mut plain = factorial_vm(6);
+        plain.entry = 5;
+        target.restore(&plain.snapshot()).unwrap();
+        assert_eq!(target.entry, 5);
+        assert!(target.debug_info().is_none());
+        assert_eq!(target.labels(), &Vec::new());
+        assert_eq!(target.location(14), "pc=14");
+    }
+
+    #[test]
+    fn should_reject_inconsistent_heap_blocks() {
+        let mut vm = VM::new();
+        vm.heap = vec![0; 16];
+        vm.allocator = HeapAllocator::restore(
+            vec![
+                HeapBlock {
+                    offset: 0,
+                    size: 8,
+                    free: false,
+                },
+                HeapBlock {
+                    offset: 4,
+                    size: 8,
+                    free: false,
+                },
+            ],
+            2,
+            0,
+        );
+        let heap_block = Err(InvalidValue {
+            field: "heap block",
+        });
+        assert_eq!(VM::new().restore(&vm.snapshot()), heap_block);
+
+        vm.allocator = HeapAllocator::restore(
+            vec![HeapBlock {
+                offset: 15,
+                size: 1,
+                free: false,
+            }],
+            1,
+            0,
+        );
+        assert!(VM::new().restore(&vm.snapshot()).is_ok());
+        vm.gc.enabled = true;
+        assert_eq!(VM::new().restore(&vm.snapshot()), heap_block);
+    }
+}
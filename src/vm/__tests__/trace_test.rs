@@ -0,0 +1,127 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+#[cfg(test)]
+mod tests {
+    use crate::vm::instruction::OpCode::{ADD, ADDI, CALLI, HLT, LOAD, LOAD32};
+    use crate::vm::trace::TraceChange::{ComparisonFlag, Register};
+    use crate::vm::trace::TraceEvent;
+    use crate::vm::trace::TraceSink::{Callback, Writer};
+    use crate::vm::vm::VM;
+    use crate::vm::vm_io::SharedBuffer;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn should_format_trace_event() {
+        let event = TraceEvent {
+            pc: 4,
+            opcode: ADD,
+            operands: vec![1, 2, 3],
+            changes: vec![
+                Register {
+                    index: 3,
+                    old: 0,
+                    new: 5,
+                },
+                ComparisonFlag {
+                    old: false,
+                    new: true,
+                },
+            ],
+        };
+        assert_eq!(
+            event.to_string(),
+            "0004 ADD $1 $2 $3 | $3: 0 -> 5, flag: false -> true"
+        );
+    }
+
+    #[test]
+    fn should_format_decoded_immediates() {
+        let event = |opcode, operands: Vec<u8>| TraceEvent {
+            pc: 0,
+            opcode,
+            operands,
+            changes: vec![],
+        };
+        assert_eq!(
+            event(LOAD, vec![0, 1, 244]).to_string(),
+            "0000 LOAD $0 #500"
+        );
+        assert_eq!(
+            event(ADDI, vec![1, 255, 254, 2]).to_string(),
+            "0000 ADDI $1 #-2 $2"
+        );
+        assert_eq!(
+            event(LOAD32, vec![3, 255, 255, 255, 255]).to_string(),
+            "0000 LOAD32 $3 #-1"
+        );
+        assert_eq!(
+            event(CALLI, vec![0, 0, 0, 18]).to_string(),
+            "0000 CALLI #18"
+        );
+        assert_eq!(event(HLT, vec![]).to_string(), "0000 HLT");
+    }
+
+    #[test]
+    fn should_trace_to_callback() {
+        let events: Rc<RefCell<Vec<TraceEvent>>> = Rc::new(RefCell::new(Vec::new()));
+        let recorded = events.clone();
+        let mut vm = VM::new();
+        vm.set_exit_banner(false);
+        vm.enable_trace(
+            Callback(Box::new(move |event| {
+                recorded.borrow_mut().push(event.clone())
+            })),
+            None,
+        );
+        vm.program = vec![1, 1, 0, 2, 1, 2, 0, 3, 2, 1, 2, 3, 0];
+        vm.run().unwrap();
+
+        let events = events.borrow();
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            events[0],
+            TraceEvent {
+                pc: 0,
+                opcode: LOAD,
+                operands: vec![1, 0, 2],
+                changes: vec![Register {
+                    index: 1,
+                    old: 0,
+                    new: 2
+                }],
+            }
+        );
+        assert_eq!(
+            events[2].changes,
+            vec![Register {
+                index: 3,
+                old: 0,
+                new: 5
+            }]
+        );
+        assert_eq!(events[3].changes, vec![]);
+    }
+
+    #[test]
+    fn should_stop_trace_at_limit() {
+        let output = SharedBuffer::new();
+        let mut vm = VM::new();
+        vm.set_exit_banner(false);
+        vm.enable_trace(Writer(Box::new(output.clone())), Some(2));
+        vm.program = vec![1, 1, 0, 2, 1, 2, 0, 3, 2, 1, 2, 3, 0];
+        vm.run().unwrap();
+
+        assert_eq!(
+            output.text(),
+            "0000 LOAD $1 #2 | $1: 0 -> 2\n\
+             0004 LOAD $2 #3 | $2: 0 -> 3\n\
+             trace limit of 2 reached\n"
+        );
+        assert_eq!(vm.tracer().unwrap().traced(), 2);
+        assert!(!vm.is_tracing());
+        assert_eq!(vm.registers[3], 5);
+    }
+}
@@ -0,0 +1,133 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::assembler::Assembler;
+    use crate::assembler::elf::DELFError::Rejected;
+    use crate::assembler::elf::DELFHeader;
+    use crate::vm::verifier::verify;
+    use crate::vm::vm::VM;
+    use crate::vm::vm_error::VmError::{
+        BadRoDataOffset, IllegalOpcode, InvalidCallTarget, InvalidRegister, PcOutOfBounds,
+    };
+
+    #[test]
+    fn should_accept_assembled_program() {
+        let mut assembler = Assembler::new();
+        let program = assembler
+            .process(
+                ".data\n\
+                 hello: .asciiz \"Hello\"\n\
+                 .code\n\
+                 main: prts @hello\n\
+                 call @done\n\
+                 jmp @end\n\
+                 done: ret\n\
+                 end: hlt",
+            )
+            .unwrap();
+        assert_eq!(VM::new().load_program(program), Ok(()));
+    }
+
+    #[test]
+    fn should_reject_illegal_opcode() {
+        assert_eq!(
+            verify(&[0x01, 0x01, 0x00, 0x02, 0xFE, 0x00], &[]),
+            Err(IllegalOpcode {
+                opcode: 0xFE,
+                pc: 4
+            })
+        );
+    }
+
+    #[test]
+    fn should_reject_truncated_instruction() {
+        assert_eq!(
+            verify(&[0x00, 0x01, 0x01], &[]),
+            Err(PcOutOfBounds { address: 3, pc: 1 })
+        );
+    }
+
+    #[test]
+    fn should_reject_register_out_of_range() {
+        // ADD $1 $2 $32
+        assert_eq!(
+            verify(&[0x02, 0x01, 0x02, 0x20], &[]),
+            Err(InvalidRegister {
+                register: 32,
+                pc: 0
+            })
+        );
+        // LOADF64 $40 #1
+        assert_eq!(
+            verify(&[0x16, 0x28, 0x00, 0x01], &[]),
+            Err(InvalidRegister {
+                register: 40,
+                pc: 0
+            })
+        );
+    }
+
+    #[test]
+    fn should_reject_jump_into_an_instruction() {
+        // JMPI 2, LOAD $1 #2
+        assert_eq!(
+            verify(&[0x54, 0x00, 0x00, 0x00, 0x07, 0x01, 0x01, 0x00, 0x02], &[]),
+            Err(PcOutOfBounds { address: 7, pc: 0 })
+        );
+        // JEI to the end of program terminates it
+        assert_eq!(verify(&[0x55, 0x00, 0x00, 0x00, 0x05], &[]), Ok(()));
+        // CALLI 1, HLT
+        assert_eq!(
+            verify(&[0x59, 0x00, 0x00, 0x00, 0x01, 0x00], &[]),
+            Err(InvalidCallTarget { address: 1, pc: 0 })
+        );
+        assert_eq!(
+            verify(&[0x59, 0x00, 0x00, 0x00, 0x06, 0x00], &[]),
+            Err(InvalidCallTarget { address: 6, pc: 0 })
+        );
+    }
+
+    #[test]
+    fn should_reject_prts_outside_of_ro_data() {
+        // LOAD $31 #6, PRTS $31
+        let program = [0x01, 0x1F, 0x00, 0x06, 0x0E, 0x1F];
+        assert_eq!(
+            verify(&program, b"Hello\0"),
+            Err(BadRoDataOffset { offset: 6, pc: 4 })
+        );
+        assert_eq!(
+            verify(&[0x01, 0x1F, 0x00, 0x00, 0x0E, 0x1F], b"Hello"),
+            Err(BadRoDataOffset { offset: 0, pc: 4 })
+        );
+        assert_eq!(
+            verify(&[0x01, 0x1F, 0x00, 0x00, 0x0E, 0x1F], b"Hello\0"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn should_not_load_rejected_program() {
+        let code = [0x02, 0x01, 0x02, 0x40];
+        let mut program = DELFHeader::for_sections(0, code.len() as u32, 0).encode_to_bytes();
+        program.extend_from_slice(&code);
+
+        let mut vm = VM::new();
+        let error = vm.load_program(program).unwrap_err();
+        assert_eq!(
+            error,
+            Rejected {
+                error: InvalidRegister {
+                    register: 64,
+                    pc: 0
+                }
+            }
+        );
+        assert!(error
+            .to_string()
+            .starts_with("Program rejected by verifier: "));
+        assert!(vm.program.is_empty());
+    }
+}
@@ -4,7 +4,21 @@
 
 #[cfg(test)]
 mod tests {
-    use crate::vm::vm::VM;
+    use crate::assembler::assembler::Assembler;
+    use crate::vm::__tests__::common::fixtures::factorial_source;
+    use crate::vm::gc::DEFAULT_GC_THRESHOLD;
+    use crate::vm::overflow_mode::OverflowMode::{Saturating, Trapping, Wrapping};
+    use crate::vm::syscall::{SYS_EXIT, SYS_PRINT_INT, SYS_READ_LINE, SYS_TIME};
+    use crate::vm::vm::{DEFAULT_STACK_SIZE, VM};
+    use crate::vm::vm_error::VmError::{
+        BadRoDataOffset, DivideByZero, HeapOutOfBounds, IllegalOpcode, IntegerOverflow,
+        InvalidAllocationSize, InvalidCallTarget, InvalidFree, InvalidRegister, PcOutOfBounds,
+        StackOverflow, StackSlotOutOfBounds, StackUnderflow, SyscallFailed, UnknownSyscall,
+    };
+    use crate::vm::vm_io::SharedBuffer;
+    use crate::vm::vm_state::VmState::{BudgetExhausted, Faulted, Halted, Running};
+    use std::io::Cursor;
+    use std::rc::Rc;
 
     #[test]
     fn should_create_vm() {
@@ -16,7 +30,7 @@ mod tests {
     fn should_halt() {
         let mut vm = VM::new();
         vm.program = vec![0, 0, 0, 0];
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.pc, 1);
     }
 
@@ -24,7 +38,7 @@ mod tests {
     fn should_load_instruction() {
         let mut vm = VM::new();
         vm.program = vec![1, 0, 1, 244]; /*LOAD 0 #500; 0b0000000111110100 = 500(oct) */
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.registers[0], 500);
     }
 
@@ -36,7 +50,7 @@ mod tests {
             1, 1, 1, 244, /*LOAD 1 #500; */
             2, 0, 1, 2,
         ]; /*ADD 0 1 2; */
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.registers[0], 500);
         assert_eq!(vm.registers[1], 500);
         assert_eq!(vm.registers[2], 1000);
@@ -50,7 +64,7 @@ mod tests {
             1, 1, 0, 244, /*LOAD 1 #244; */
             3, 0, 1, 2,
         ]; /*SUB 0 1 2; */
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.registers[0], 500);
         assert_eq!(vm.registers[1], 244);
         assert_eq!(vm.registers[2], 256);
@@ -64,7 +78,7 @@ mod tests {
             1, 1, 0, 3, /*LOAD 1 #3; */
             4, 0, 1, 2,
         ]; /*MUL 0 1 2; */
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.registers[0], 500);
         assert_eq!(vm.registers[1], 3);
         assert_eq!(vm.registers[2], 1500);
@@ -78,7 +92,7 @@ mod tests {
             1, 1, 0, 3, /*LOAD 1 #3; */
             5, 0, 1, 2,
         ]; /*DIV 0 1 2; */
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.registers[0], 500);
         assert_eq!(vm.registers[1], 3);
         assert_eq!(vm.registers[2], 166);
@@ -89,8 +103,8 @@ mod tests {
     fn should_jmp() {
         let mut vm = VM::new();
         vm.program = vec![1, 0, 0, 1 /*LOAD 0 #1; */, 6, 0]; /*JMP 0; */
-        vm.run_once();
-        vm.run_once();
+        vm.run_once().unwrap();
+        vm.run_once().unwrap();
         assert_eq!(vm.pc, 1);
     }
 
@@ -98,9 +112,9 @@ mod tests {
     fn should_jmpf() {
         let mut vm = VM::new();
         vm.program = vec![1, 0, 0, 3 /*LOAD 0 #3; */, 7, 0]; /*JMPF 0; */
-        vm.run_once();
+        vm.run_once().unwrap();
         assert_eq!(vm.pc, 4);
-        vm.run_once();
+        vm.run_once().unwrap();
         assert_eq!(vm.pc, 9);
     }
 
@@ -108,9 +122,9 @@ mod tests {
     fn should_jmpb() {
         let mut vm = VM::new();
         vm.program = vec![1, 0, 0, 3 /*LOAD 0 #3; */, 8, 0]; /*JMPB 0; */
-        vm.run_once();
+        vm.run_once().unwrap();
         assert_eq!(vm.pc, 4);
-        vm.run_once();
+        vm.run_once().unwrap();
         assert_eq!(vm.pc, 3);
     }
 
@@ -126,7 +140,7 @@ mod tests {
             9, 2, 3,
         ]; /*EQ 2 3; */
 
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.comparison_flag, false);
     }
 
@@ -141,12 +155,12 @@ mod tests {
             10, 2,
         ]; /*JEQ 2; */
 
-        vm.run_once(); /*LOAD 0 #500; */
-        vm.run_once(); /*LOAD 1 #500; */
-        vm.run_once(); /*EQ 0 1; */
+        vm.run_once().unwrap(); /*LOAD 0 #500; */
+        vm.run_once().unwrap(); /*LOAD 1 #500; */
+        vm.run_once().unwrap(); /*EQ 0 1; */
         assert_eq!(vm.comparison_flag, true);
-        vm.run_once(); /*LOAD 2 #3; */
-        vm.run_once(); /*JEQ 2; */
+        vm.run_once().unwrap(); /*LOAD 2 #3; */
+        vm.run_once().unwrap(); /*JEQ 2; */
         assert_eq!(vm.pc, 3);
     }
 
@@ -161,12 +175,12 @@ mod tests {
             15, 2,
         ]; /*JNE 2; */
 
-        vm.run_once(); /*LOAD 0 #500; */
-        vm.run_once(); /*LOAD 1 #499; */
-        vm.run_once(); /*EQ 0 1; */
+        vm.run_once().unwrap(); /*LOAD 0 #500; */
+        vm.run_once().unwrap(); /*LOAD 1 #499; */
+        vm.run_once().unwrap(); /*EQ 0 1; */
         assert_eq!(vm.comparison_flag, false);
-        vm.run_once(); /*LOAD 2 #3; */
-        vm.run_once(); /*jne 2; */
+        vm.run_once().unwrap(); /*LOAD 2 #3; */
+        vm.run_once().unwrap(); /*jne 2; */
         assert_eq!(vm.pc, 3);
     }
 
@@ -179,7 +193,7 @@ mod tests {
             18, 0, 1,
         ]; /*LT 0 1; */
 
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.comparison_flag, true);
     }
 
@@ -192,7 +206,7 @@ mod tests {
             20, 0, 1,
         ]; /*GT 0 1; */
 
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.comparison_flag, true);
     }
 
@@ -207,12 +221,12 @@ mod tests {
             16, 2,
         ]; /*JLT 2; */
 
-        vm.run_once(); /*LOAD 0 #499; */
-        vm.run_once(); /*LOAD 1 #500; */
-        vm.run_once(); /*LT 0 1; */
+        vm.run_once().unwrap(); /*LOAD 0 #499; */
+        vm.run_once().unwrap(); /*LOAD 1 #500; */
+        vm.run_once().unwrap(); /*LT 0 1; */
         assert_eq!(vm.comparison_flag, true);
-        vm.run_once(); /*LOAD 2 #3; */
-        vm.run_once(); /*JLT 2; */
+        vm.run_once().unwrap(); /*LOAD 2 #3; */
+        vm.run_once().unwrap(); /*JLT 2; */
         assert_eq!(vm.pc, 3);
     }
 
@@ -227,12 +241,12 @@ mod tests {
             17, 2,
         ]; /*JGT 2; */
 
-        vm.run_once(); /*LOAD 0 #500; */
-        vm.run_once(); /*LOAD 1 #499; */
-        vm.run_once(); /*GT 0 1; */
+        vm.run_once().unwrap(); /*LOAD 0 #500; */
+        vm.run_once().unwrap(); /*LOAD 1 #499; */
+        vm.run_once().unwrap(); /*GT 0 1; */
         assert_eq!(vm.comparison_flag, true);
-        vm.run_once(); /*LOAD 2 #3; */
-        vm.run_once(); /*JGT 2; */
+        vm.run_once().unwrap(); /*LOAD 2 #3; */
+        vm.run_once().unwrap(); /*JGT 2; */
         assert_eq!(vm.pc, 3);
     }
 
@@ -241,8 +255,8 @@ mod tests {
         let mut vm = VM::new();
         vm.program = vec![1, 0, 1, 244 /*LOAD 0 #500; */, 12, 0]; /*INC $0; */
 
-        vm.run_once(); /*LOAD 0 #500; */
-        vm.run_once(); /*INC $0; */
+        vm.run_once().unwrap(); /*LOAD 0 #500; */
+        vm.run_once().unwrap(); /*INC $0; */
         assert_eq!(vm.registers[0], 501);
     }
 
@@ -251,8 +265,8 @@ mod tests {
         let mut vm = VM::new();
         vm.program = vec![1, 0, 1, 244 /*LOAD 0 #500; */, 13, 0]; /*DEC $0; */
 
-        vm.run_once(); /*LOAD 0 #500; */
-        vm.run_once(); /*DEC $0; */
+        vm.run_once().unwrap(); /*LOAD 0 #500; */
+        vm.run_once().unwrap(); /*DEC $0; */
         assert_eq!(vm.registers[0], 499);
     }
 
@@ -260,7 +274,7 @@ mod tests {
     fn should_opcode_and() {
         let mut vm = VM::new();
         vm.program = vec![1, 0, 0, 3, 1, 1, 0, 7, 33, 0, 1, 2];
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.registers[2], 3 & 7);
     }
 
@@ -268,7 +282,7 @@ mod tests {
     fn should_opcode_or() {
         let mut vm = VM::new();
         vm.program = vec![1, 0, 0, 3, 1, 1, 0, 7, 34, 0, 1, 2];
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.registers[2], 3 | 7);
     }
 
@@ -276,7 +290,7 @@ mod tests {
     fn should_opcode_xor() {
         let mut vm = VM::new();
         vm.program = vec![1, 0, 0, 3, 1, 1, 0, 7, 35, 0, 1, 2];
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.registers[2], 3 ^ 7);
     }
 
@@ -284,18 +298,82 @@ mod tests {
     fn should_opcode_not() {
         let mut vm = VM::new();
         vm.program = vec![1, 0, 0, 3, 36, 0, 1];
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.registers[1], !3);
     }
 
     #[test]
     fn should_aloc() {
         let mut vm = VM::new();
-        vm.program = vec![1, 0, 1, 244 /*LOAD 0 #500; */, 11, 0]; /*ALOC $0; */
+        vm.program = vec![
+            1, 0, 1, 244, /*LOAD 0 #500; */
+            11, 0, 1, /*ALOC $0 $1; */
+            11, 0, 2, /*ALOC $0 $2; */
+        ];
+
+        vm.run_once().unwrap(); /*LOAD 0 #500; */
+        vm.run_once().unwrap(); /*ALOC $0 $1; */
+        assert_eq!(vm.heap.len(), 504);
+        assert_eq!(vm.registers[1], 0);
+        vm.run_once().unwrap(); /*ALOC $0 $2; */
+        assert_eq!(vm.heap.len(), 1008);
+        assert_eq!(vm.registers[2], 504);
+    }
+
+    #[test]
+    fn should_reuse_block_after_free() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            1, 0, 0, 16, /*LOAD $0 #16; */
+            11, 0, 1, /*ALOC $0 $1; */
+            11, 0, 2, /*ALOC $0 $2; */
+            45, 0, 1, 0, 0, /*STW $0 $1 #0; */
+            47, 1, /*FREE $1; */
+            11, 0, 3, /*ALOC $0 $3; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.registers[1], 0);
+        assert_eq!(vm.registers[2], 16);
+        assert_eq!(vm.registers[3], 0);
+        assert_eq!(vm.heap.len(), 32);
+        assert_eq!(vm.heap[0..4].to_vec(), vec![0, 0, 0, 0]);
+
+        let stats = vm.heap_stats();
+        assert_eq!(stats.used_bytes, 32);
+        assert_eq!(stats.used_blocks, 2);
+        assert_eq!(stats.allocations, 3);
+        assert_eq!(stats.frees, 1);
+    }
 
-        vm.run_once(); /*LOAD 0 #500; */
-        vm.run_once(); /*ALOC $0; */
-        assert_eq!(vm.heap.len(), 500);
+    #[test]
+    fn should_fault_when_free_unallocated_address() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            1, 0, 0, 16, /*LOAD $0 #16; */
+            11, 0, 1, /*ALOC $0 $1; */
+            47, 0, /*FREE $0; */
+        ];
+        assert_eq!(vm.run(), Err(InvalidFree { address: 16, pc: 7 }));
+    }
+
+    #[test]
+    fn should_fault_when_free_twice() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            1, 0, 0, 16, /*LOAD $0 #16; */
+            11, 0, 1, /*ALOC $0 $1; */
+            47, 1, /*FREE $1; */
+            47, 1, /*FREE $1; */
+        ];
+        assert_eq!(vm.run(), Err(InvalidFree { address: 0, pc: 9 }));
+    }
+
+    #[test]
+    fn should_fault_when_aloc_negative_size() {
+        let mut vm = VM::new();
+        vm.registers[0] = -1;
+        vm.program = vec![11, 0, 1]; /*ALOC $0 $1; */
+        assert_eq!(vm.run(), Err(InvalidAllocationSize { size: -1, pc: 0 }));
     }
 
     #[test]
@@ -308,7 +386,7 @@ mod tests {
             37, 1,
         ]; /*PUSH $1; */
 
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.stack.len(), 2);
         assert_eq!(vm.sp, 2);
         assert_eq!(vm.stack, vec![500, 499]);
@@ -325,23 +403,22 @@ mod tests {
             38, 2,
         ]; /*POP $2*/
 
-        vm.run_once();
-        vm.run_once();
+        vm.run_once().unwrap();
+        vm.run_once().unwrap();
         assert_eq!(vm.stack.len(), 1);
         assert_eq!(vm.stack, vec![500]);
         assert_eq!(vm.sp, 1);
-        vm.run_once();
-        vm.run_once();
+        vm.run_once().unwrap();
+        vm.run_once().unwrap();
         assert_eq!(vm.stack.len(), 2);
         assert_eq!(vm.stack, vec![500, 499]);
         assert_eq!(vm.sp, 2);
-        vm.run_once();
+        vm.run_once().unwrap();
         assert_eq!(vm.registers[2], 499);
         assert_eq!(vm.sp, 1);
     }
 
     #[test]
-    #[should_panic(expected = "Error: Stack Overflow.")]
     fn should_stack_overflow_when_recursion_call() {
         let mut vm = VM::new();
         vm.program = vec![
@@ -350,7 +427,13 @@ mod tests {
             1, 3, 0, 10, /*LOAD $3 10*/
             39, 3,
         ]; /*call $3*/
-        vm.run();
+        assert_eq!(
+            vm.run(),
+            Err(StackOverflow {
+                depth: 1048575,
+                pc: 10
+            })
+        );
     }
 
     #[test]
@@ -365,9 +448,10 @@ mod tests {
             1, 4, 1, 240, /*LOAD 1 #496; */
             1, 5, 1, 239,
         ]; /*LOAD 1 #495; */
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.stack.len(), 2);
-        assert_eq!(vm.bp, 0);
+        assert_eq!(vm.bp, 2);
+        assert_eq!(vm.sp, 2);
         assert_eq!(vm.stack, vec![6, 0]);
         assert_eq!(vm.registers[0], 10);
         assert_eq!(vm.registers[1], 0);
@@ -392,7 +476,7 @@ mod tests {
             1, 5, 1, 239, /*LOAD 5 #495; */
             40,
         ]; /*ret*/
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.stack.len(), 0);
         assert_eq!(vm.bp, 0);
         assert_eq!(vm.registers[0], 11);
@@ -404,12 +488,77 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Unrecognized opcode found, Terminated.")]
     fn should_opcode_igl() {
         let mut vm = VM::new();
         vm.program = vec![200, 0, 0, 0];
-        vm.run();
-        assert_eq!(vm.pc, 1);
+        assert_eq!(vm.run(), Err(IllegalOpcode { opcode: 200, pc: 0 }));
+        assert_eq!(vm.pc, 0);
+    }
+
+    #[test]
+    fn should_fault_when_divide_by_zero() {
+        let mut vm = VM::new();
+        vm.program = vec![1, 0, 1, 244 /*LOAD 0 #500; */, 5, 0, 1, 2]; /*DIV 0 1 2; */
+        assert_eq!(vm.run(), Err(DivideByZero { pc: 4 }));
+        assert_eq!(vm.pc, 4);
+        assert_eq!(vm.registers[0], 500);
+    }
+
+    #[test]
+    fn should_fault_when_pop_empty_stack() {
+        let mut vm = VM::new();
+        vm.program = vec![38, 0]; /*POP $0*/
+        assert_eq!(vm.run(), Err(StackUnderflow { depth: 0, pc: 0 }));
+    }
+
+    #[test]
+    fn should_fault_when_ret_without_call() {
+        let mut vm = VM::new();
+        vm.program = vec![40]; /*ret*/
+        assert_eq!(vm.run(), Err(StackUnderflow { depth: 0, pc: 0 }));
+    }
+
+    #[test]
+    fn should_fault_when_register_out_of_range() {
+        let mut vm = VM::new();
+        vm.program = vec![1, 32, 0, 1]; /*LOAD $32 #1*/
+        assert_eq!(
+            vm.run(),
+            Err(InvalidRegister {
+                register: 32,
+                pc: 0
+            })
+        );
+    }
+
+    #[test]
+    fn should_fault_when_instruction_truncated() {
+        let mut vm = VM::new();
+        vm.program = vec![1, 0, 1]; /*LOAD $0 #?*/
+        assert_eq!(vm.run(), Err(PcOutOfBounds { address: 3, pc: 0 }));
+    }
+
+    #[test]
+    fn should_fault_when_jump_before_program() {
+        let mut vm = VM::new();
+        vm.program = vec![1, 0, 0, 10 /*LOAD 0 #10; */, 8, 0]; /*JMPB 0; */
+        assert_eq!(vm.run(), Err(PcOutOfBounds { address: -4, pc: 4 }));
+    }
+
+    #[test]
+    fn should_fault_when_prts_string_not_terminated() {
+        let mut vm = VM::new();
+        vm.ro_data = vec![72, 105];
+        vm.program = vec![1, 0, 0, 0 /*LOAD 0 #0; */, 14, 0]; /*PRTS $0*/
+        assert_eq!(vm.run(), Err(BadRoDataOffset { offset: 0, pc: 4 }));
+    }
+
+    #[test]
+    fn should_fault_when_prts_offset_out_of_ro_data() {
+        let mut vm = VM::new();
+        vm.ro_data = vec![72, 105, 0];
+        vm.program = vec![1, 0, 0, 3 /*LOAD 0 #3; */, 14, 0]; /*PRTS $0*/
+        assert_eq!(vm.run(), Err(BadRoDataOffset { offset: 3, pc: 4 }));
     }
 
     #[test]
@@ -425,7 +574,7 @@ mod tests {
             1, 31, 0, 12, // LOAD    $31 #0  #12
             15, 31,
         ]; // JNE     $31
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.pc, 25);
         assert_eq!(vm.registers[2], 51);
     }
@@ -443,8 +592,940 @@ mod tests {
             0x01, 0x01, 0x1F, 0x00, 0x00, 0x0E, 0x1F, 0x0D, 0x01, 0x0C, 0x02, 0x01, 0x1F, 0x00,
             0x0C, 0x0F, 0x1F, 0x01, 0x1F, 0x00, 0x0E, 0x0E, 0x1F,
         ];
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.pc, 37);
         assert_eq!(vm.registers[2], 51);
     }
+
+    #[test]
+    fn should_halt_with_state() {
+        let mut vm = VM::new();
+        vm.program = vec![1, 0, 1, 244 /*LOAD 0 #500; */, 0]; /*HLT*/
+        assert_eq!(vm.run(), Ok(Halted(0)));
+        assert_eq!(vm.state(), Halted(0));
+        assert_eq!(vm.executed_instructions(), 2);
+    }
+
+    #[test]
+    fn should_fault_with_state() {
+        let mut vm = VM::new();
+        vm.program = vec![38, 0]; /*POP $0*/
+        assert!(vm.run().is_err());
+        assert_eq!(vm.state(), Faulted);
+    }
+
+    #[test]
+    fn should_run_for_steps() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            1, 0, 0, 0, // LOAD    $0  #0
+            12, 1, // INC     $1
+            6, 0, // JMP     $0
+        ];
+        assert_eq!(vm.run_for(7), Ok(Running));
+        assert_eq!(vm.registers[1], 2);
+        assert_eq!(vm.pc, 4);
+        assert_eq!(vm.run_for(2), Ok(Running));
+        assert_eq!(vm.registers[1], 3);
+        assert_eq!(vm.executed_instructions(), 9);
+    }
+
+    #[test]
+    fn should_run_for_until_halt() {
+        let mut vm = VM::new();
+        vm.program = vec![12, 1 /*INC $1*/, 0]; /*HLT*/
+        assert_eq!(vm.run_for(100), Ok(Halted(0)));
+        assert_eq!(vm.executed_instructions(), 2);
+    }
+
+    #[test]
+    fn should_not_charge_end_of_program() {
+        let mut vm = VM::new();
+        vm.program = vec![1, 0, 1, 244]; /*LOAD $0 #500; */
+        assert_eq!(vm.run(), Ok(Halted(0)));
+        assert_eq!(vm.executed_instructions(), 1);
+
+        let mut vm = VM::new();
+        vm.program = vec![1, 0, 1, 244]; /*LOAD $0 #500; */
+        assert_eq!(vm.run_for(1), Ok(Halted(0)));
+
+        let mut vm = VM::new();
+        vm.program = vec![1, 0, 1, 244]; /*LOAD $0 #500; */
+        vm.set_instruction_budget(Some(1));
+        assert_eq!(vm.run(), Ok(Halted(0)));
+        assert_eq!(vm.instruction_budget(), Some(0));
+    }
+
+    #[test]
+    fn should_run_until_breakpoint() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            1, 0, 0, 0, // LOAD    $0  #0
+            12, 1, // INC     $1
+            6, 0, // JMP     $0
+        ];
+        assert_eq!(vm.run_until(6), Ok(Running));
+        assert_eq!(vm.pc, 6);
+        assert_eq!(vm.registers[1], 1);
+        assert_eq!(vm.run_until(6), Ok(Running));
+        assert_eq!(vm.pc, 6);
+        assert_eq!(vm.registers[1], 2);
+    }
+
+    #[test]
+    fn should_stop_infinite_loop_when_budget_exhausted() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            1, 0, 0, 0, // LOAD    $0  #0
+            12, 1, // INC     $1
+            6, 0, // JMP     $0
+        ];
+        vm.set_instruction_budget(Some(1000));
+        assert_eq!(vm.run(), Ok(BudgetExhausted));
+        assert_eq!(vm.state(), BudgetExhausted);
+        assert_eq!(vm.instruction_budget(), Some(0));
+        assert_eq!(vm.executed_instructions(), 1000);
+
+        vm.set_instruction_budget(Some(3));
+        assert_eq!(vm.run(), Ok(BudgetExhausted));
+        assert_eq!(vm.executed_instructions(), 1003);
+    }
+
+    #[test]
+    fn should_store_and_load_byte() {
+        let mut vm = VM::new();
+        vm.heap = vec![0; 8];
+        vm.program = vec![
+            1, 0, 0, 2, /*LOAD $0 #2; */
+            1, 1, 1, 44, /*LOAD $1 #300; */
+            44, 1, 0, 0, 3, /*STB $1 $0 #3; */
+            41, 2, 0, 0, 3, /*LDB $2 $0 #3; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.heap, vec![0, 0, 0, 0, 0, 44, 0, 0]);
+        assert_eq!(vm.registers[2], 44);
+    }
+
+    #[test]
+    fn should_store_and_load_word() {
+        let mut vm = VM::new();
+        vm.heap = vec![0; 8];
+        vm.program = vec![
+            1, 0, 0, 4, /*LOAD $0 #4; */
+            1, 1, 1, 244, /*LOAD $1 #500; */
+            45, 1, 0, 0, 0, /*STW $1 $0 #0; */
+            42, 2, 0, 0, 0, /*LDW $2 $0 #0; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.heap, vec![0, 0, 0, 0, 0, 0, 1, 244]);
+        assert_eq!(vm.registers[2], 500);
+    }
+
+    #[test]
+    fn should_store_and_load_f64() {
+        let mut vm = VM::new();
+        vm.heap = vec![0; 16];
+        vm.program = vec![
+            22, 1, 0, 3, /*LOADF64 $1 #3; */
+            46, 1, 0, 0, 8, /*STF64 $1 $0 #8; */
+            43, 2, 0, 0, 8, /*LDF64 $2 $0 #8; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.heap[8..16].to_vec(), 3.0f64.to_be_bytes().to_vec());
+        assert_eq!(vm.float_registers[2], 3.0);
+    }
+
+    #[test]
+    fn should_fault_when_load_out_of_heap() {
+        let mut vm = VM::new();
+        vm.heap = vec![0; 8];
+        vm.program = vec![
+            1, 0, 0, 6, /*LOAD $0 #6; */
+            42, 1, 0, 0, 0, /*LDW $1 $0 #0; */
+        ];
+        assert_eq!(vm.run(), Err(HeapOutOfBounds { address: 6, pc: 4 }));
+    }
+
+    #[test]
+    fn should_fault_when_store_before_heap() {
+        let mut vm = VM::new();
+        vm.heap = vec![0; 8];
+        vm.registers[0] = -4;
+        vm.program = vec![44, 1, 0, 0, 2]; /*STB $1 $0 #2; */
+        assert_eq!(vm.run(), Err(HeapOutOfBounds { address: -2, pc: 0 }));
+        assert_eq!(vm.heap, vec![0; 8]);
+    }
+
+    #[test]
+    fn should_aloc_gc_block_after_header() {
+        let mut vm = VM::new();
+        vm.enable_gc(DEFAULT_GC_THRESHOLD).unwrap();
+        vm.program = vec![
+            1, 0, 0, 16, /*LOAD $0 #16; */
+            11, 0, 1, /*ALOC $0 $1; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.registers[1], 8);
+        assert_eq!(vm.heap.len(), 24);
+        assert_eq!(vm.heap[0..4].to_vec(), vec![0, 0, 0, 16]);
+    }
+
+    #[test]
+    fn should_collect_unreachable_blocks() {
+        let mut vm = VM::new();
+        vm.enable_gc(DEFAULT_GC_THRESHOLD).unwrap();
+        vm.program = vec![
+            1, 0, 0, 16, /*LOAD $0 #16; */
+            11, 0, 1, /*ALOC $0 $1; */
+            11, 0, 2, /*ALOC $0 $2; */
+            11, 0, 3, /*ALOC $0 $3; */
+            37, 3, /*PUSH $3; */
+            1, 2, 0, 0, /*LOAD $2 #0; */
+            1, 3, 0, 0, /*LOAD $3 #0; */
+        ];
+        vm.run().unwrap();
+        let stats = vm.collect_garbage();
+        assert_eq!(stats.collections, 1);
+        assert_eq!(stats.reclaimed_blocks, 1);
+        assert_eq!(stats.reclaimed_bytes, 24);
+        assert_eq!(stats.live_blocks, 2);
+        assert_eq!(vm.heap_stats().free_bytes, 24);
+    }
+
+    #[test]
+    fn should_keep_blocks_reachable_from_heap() {
+        let mut vm = VM::new();
+        vm.enable_gc(DEFAULT_GC_THRESHOLD).unwrap();
+        vm.program = vec![
+            1, 0, 0, 16, /*LOAD $0 #16; */
+            11, 0, 1, /*ALOC $0 $1; */
+            11, 0, 2, /*ALOC $0 $2; */
+            45, 2, 1, 0, 4, /*STW $2 $1 #4; */
+            1, 2, 0, 0, /*LOAD $2 #0; */
+        ];
+        vm.run().unwrap();
+        let stats = vm.collect_garbage();
+        assert_eq!(stats.reclaimed_blocks, 0);
+        assert_eq!(stats.live_blocks, 2);
+
+        vm.registers[0] = 0;
+        vm.registers[1] = 0;
+        let stats = vm.collect_garbage();
+        assert_eq!(stats.reclaimed_blocks, 2);
+        assert_eq!(stats.live_blocks, 0);
+    }
+
+    #[test]
+    fn should_collect_after_program_overwrites_block_header() {
+        let mut vm = VM::new();
+        vm.enable_gc(DEFAULT_GC_THRESHOLD).unwrap();
+        vm.program = vec![
+            1, 0, 0, 16, /*LOAD $0 #16; */
+            11, 0, 1, /*ALOC $0 $1; */
+            1, 2, 16, 0, /*LOAD $2 #4096; */
+            1, 3, 0, 0, /*LOAD $3 #0; */
+            45, 2, 3, 0, 0, /*STW $2 $3 #0; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.heap[0..4].to_vec(), vec![0, 0, 16, 0]);
+        let stats = vm.collect_garbage();
+        assert_eq!(stats.live_blocks, 1);
+        assert_eq!(stats.reclaimed_blocks, 0);
+    }
+
+    #[test]
+    fn should_keep_zero_size_block_alive_until_freed() {
+        let mut vm = VM::new();
+        vm.enable_gc(DEFAULT_GC_THRESHOLD).unwrap();
+        vm.program = vec![
+            1, 0, 0, 0, /*LOAD $0 #0; */
+            11, 0, 1, /*ALOC $0 $1; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.registers[1], 8);
+        let stats = vm.collect_garbage();
+        assert_eq!(stats.live_blocks, 1);
+        assert_eq!(stats.reclaimed_blocks, 0);
+
+        vm.program.extend_from_slice(&[47, 1]); /*FREE $1; */
+        assert_eq!(vm.run(), Ok(Halted(0)));
+    }
+
+    #[test]
+    fn should_collect_when_heap_crosses_threshold() {
+        let mut vm = VM::new();
+        vm.enable_gc(64).unwrap();
+        vm.program = vec![
+            1, 0, 0, 24, /*LOAD $0 #24; */
+            1, 2, 0, 10, /*LOAD $2 #10; */
+            1, 5, 0, 12, /*LOAD $5 #12; */
+            11, 0, 1, /*ALOC $0 $1; */
+            13, 2, /*DEC $2; */
+            9, 2, 4, /*EQ $2 $4; */
+            15, 5, /*JNE $5; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.heap_stats().allocations, 10);
+        assert!(vm.gc_stats().collections > 0);
+        assert!(vm.heap.len() < 320);
+    }
+
+    #[test]
+    fn should_not_enable_gc_after_allocation() {
+        let mut vm = VM::new();
+        vm.program = vec![1, 0, 0, 16 /*LOAD $0 #16; */, 11, 0, 1]; /*ALOC $0 $1; */
+        vm.run().unwrap();
+        assert!(vm.enable_gc(DEFAULT_GC_THRESHOLD).is_err());
+    }
+
+    #[test]
+    fn should_load32_instruction() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            48, 0, 0, 1, 17, 112, /*LOAD32 $0 #70000; */
+            48, 1, 255, 255, 255, 251, /*LOAD32 $1 #-5; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.registers[0], 70000);
+        assert_eq!(vm.registers[1], -5);
+    }
+
+    #[test]
+    fn should_convert_between_int_and_float() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            48, 0, 255, 255, 255, 249, /*LOAD32 $0 #-7; */
+            49, 0, 1, /*ITOF $0 $1; */
+            22, 2, 0, 2, /*LOADF64 $2 #2; */
+            26, 1, 2, 3, /*DIVF64 $1 $2 $3; */
+            50, 3, 4, /*FTOI $3 $4; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.float_registers[1], -7.0);
+        assert_eq!(vm.float_registers[3], -3.5);
+        assert_eq!(vm.registers[4], -3);
+    }
+
+    #[test]
+    fn should_move_negate_abs_and_sqrt_f64() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            22, 0, 0, 16, /*LOADF64 $0 #16; */
+            51, 0, 1, /*MOVF64 $0 $1; */
+            52, 1, 2, /*NEGF64 $1 $2; */
+            53, 2, 3, /*ABSF64 $2 $3; */
+            54, 3, 4, /*SQRTF64 $3 $4; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.float_registers[1], 16.0);
+        assert_eq!(vm.float_registers[2], -16.0);
+        assert_eq!(vm.float_registers[3], 16.0);
+        assert_eq!(vm.float_registers[4], 4.0);
+    }
+
+    #[test]
+    fn should_cmp_f64_into_register() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            22, 0, 0, 1, /*LOADF64 $0 #1; */
+            22, 1, 0, 2, /*LOADF64 $1 #2; */
+            55, 0, 1, 2, /*CMPF64 $0 $1 $2; */
+            55, 1, 0, 3, /*CMPF64 $1 $0 $3; */
+            55, 1, 1, 4, /*CMPF64 $1 $1 $4; */
+        ];
+        vm.registers[4] = 9;
+        vm.run().unwrap();
+        assert_eq!(vm.registers[2], -1);
+        assert_eq!(vm.registers[3], 1);
+        assert_eq!(vm.registers[4], 0);
+    }
+
+    #[test]
+    fn should_shift_left_and_right() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            1, 0, 0, 3, /*LOAD $0 #3; */
+            1, 1, 0, 4, /*LOAD $1 #4; */
+            48, 2, 255, 255, 255, 240, /*LOAD32 $2 #-16; */
+            56, 0, 1, 3, /*SHL $0 $1 $3; */
+            57, 2, 1, 4, /*SHR $2 $1 $4; */
+            58, 2, 1, 5, /*SAR $2 $1 $5; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.registers[3], 48);
+        assert_eq!(vm.registers[4], 0x0fff_ffff);
+        assert_eq!(vm.registers[5], -1);
+    }
+
+    #[test]
+    fn should_mask_shift_amount() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            1, 0, 0, 1, /*LOAD $0 #1; */
+            1, 1, 0, 33, /*LOAD $1 #33; */
+            56, 0, 1, 2, /*SHL $0 $1 $2; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.registers[2], 2);
+    }
+
+    #[test]
+    fn should_mod_and_read_remainder() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            48, 0, 255, 255, 255, 249, /*LOAD32 $0 #-7; */
+            1, 1, 0, 3, /*LOAD $1 #3; */
+            59, 0, 1, 2, /*MOD $0 $1 $2; */
+            5, 0, 1, 3, /*DIV $0 $1 $3; */
+            62, 4, /*LOADREM $4; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.registers[2], -1);
+        assert_eq!(vm.registers[3], -2);
+        assert_eq!(vm.registers[4], -1);
+        assert_eq!(vm.remainder(), -1);
+    }
+
+    #[test]
+    fn should_divide_unsigned() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            48, 0, 255, 255, 255, 255, /*LOAD32 $0 #-1; */
+            1, 1, 0, 16, /*LOAD $1 #16; */
+            60, 0, 1, 2, /*DIVU $0 $1 $2; */
+            61, 0, 1, 3, /*MODU $0 $1 $3; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.registers[2], 0x0fff_ffff);
+        assert_eq!(vm.registers[3], 15);
+        assert_eq!(vm.remainder(), 15);
+    }
+
+    #[test]
+    fn should_fault_on_mod_by_zero() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            1, 0, 0, 3, /*LOAD $0 #3; */
+            59, 0, 1, 2, /*MOD $0 $1 $2; */
+        ];
+        assert_eq!(vm.run(), Err(DivideByZero { pc: 4 }));
+
+        let mut vm = VM::new();
+        vm.program = vec![61, 0, 1, 2]; /*MODU $0 $1 $2; */
+        assert_eq!(vm.run(), Err(DivideByZero { pc: 0 }));
+    }
+
+    #[test]
+    fn should_wrap_on_overflow() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            48, 0, 127, 255, 255, 255, /*LOAD32 $0 #2147483647; */
+            1, 1, 0, 2, /*LOAD $1 #2; */
+            63, 0, 1, 2, /*ADDWRAP $0 $1 $2; */
+            64, 2, 1, 3, /*SUBWRAP $2 $1 $3; */
+            65, 0, 1, 4, /*MULWRAP $0 $1 $4; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.registers[2], i32::min_value() + 1);
+        assert_eq!(vm.registers[3], i32::max_value());
+        assert_eq!(vm.registers[4], -2);
+    }
+
+    #[test]
+    fn should_fault_on_checked_overflow() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            48, 0, 127, 255, 255, 255, /*LOAD32 $0 #2147483647; */
+            1, 1, 0, 1, /*LOAD $1 #1; */
+            67, 0, 1, 2, /*SUBCHK $0 $1 $2; */
+            66, 0, 1, 3, /*ADDCHK $0 $1 $3; */
+        ];
+        assert_eq!(vm.run(), Err(IntegerOverflow { pc: 14 }));
+        assert_eq!(vm.registers[2], i32::max_value() - 1);
+        assert_eq!(vm.registers[3], 0);
+        assert_eq!(vm.pc, 14);
+
+        let mut vm = VM::new();
+        vm.program = vec![
+            48, 0, 127, 255, 255, 255, /*LOAD32 $0 #2147483647; */
+            1, 1, 0, 2, /*LOAD $1 #2; */
+            68, 0, 1, 2, /*MULCHK $0 $1 $2; */
+        ];
+        assert_eq!(vm.run(), Err(IntegerOverflow { pc: 10 }));
+    }
+
+    fn get_overflow_program() -> Vec<u8> {
+        vec![
+            48, 0, 127, 255, 255, 255, /*LOAD32 $0 #2147483647; */
+            48, 1, 128, 0, 0, 0, /*LOAD32 $1 #-2147483648; */
+            1, 2, 0, 2, /*LOAD $2 #2; */
+            2, 0, 2, 3, /*ADD $0 $2 $3; */
+            3, 1, 2, 4, /*SUB $1 $2 $4; */
+            4, 0, 2, 5, /*MUL $0 $2 $5; */
+            12, 0, /*INC $0; */
+            13, 1, /*DEC $1; */
+        ]
+    }
+
+    #[test]
+    fn should_wrap_arithmetic_by_default() {
+        let mut vm = VM::new();
+        assert_eq!(vm.overflow_mode(), Wrapping);
+        vm.program = get_overflow_program();
+        vm.run().unwrap();
+        assert_eq!(vm.registers[3], i32::min_value() + 1);
+        assert_eq!(vm.registers[4], i32::max_value() - 1);
+        assert_eq!(vm.registers[5], -2);
+        assert_eq!(vm.registers[0], i32::min_value());
+        assert_eq!(vm.registers[1], i32::max_value());
+    }
+
+    #[test]
+    fn should_saturate_arithmetic() {
+        let mut vm = VM::new();
+        vm.set_overflow_mode(Saturating);
+        vm.program = get_overflow_program();
+        vm.run().unwrap();
+        assert_eq!(vm.registers[3], i32::max_value());
+        assert_eq!(vm.registers[4], i32::min_value());
+        assert_eq!(vm.registers[5], i32::max_value());
+        assert_eq!(vm.registers[0], i32::max_value());
+        assert_eq!(vm.registers[1], i32::min_value());
+    }
+
+    #[test]
+    fn should_trap_arithmetic_at_faulting_pc() {
+        let mut vm = VM::new();
+        vm.set_overflow_mode(Trapping);
+        vm.program = get_overflow_program();
+        assert_eq!(vm.run(), Err(IntegerOverflow { pc: 16 }));
+        assert_eq!(vm.state(), Faulted);
+        assert_eq!(vm.registers[3], 0);
+
+        let mut vm = VM::new();
+        vm.set_overflow_mode(Trapping);
+        vm.program = vec![
+            48, 1, 128, 0, 0, 0, /*LOAD32 $1 #-2147483648; */
+            13, 1, /*DEC $1; */
+        ];
+        assert_eq!(vm.run(), Err(IntegerOverflow { pc: 6 }));
+        assert_eq!(vm.registers[1], i32::min_value());
+    }
+
+    #[test]
+    fn should_not_trap_arithmetic_in_range() {
+        let mut vm = VM::new();
+        vm.set_overflow_mode(Trapping);
+        vm.program = vec![
+            1, 0, 0, 7, /*LOAD $0 #7; */
+            1, 1, 0, 6, /*LOAD $1 #6; */
+            4, 0, 1, 2, /*MUL $0 $1 $2; */
+            12, 2, /*INC $2; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.registers[2], 43);
+    }
+
+    #[test]
+    fn should_set_register_on_int_condition() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            1, 0, 0, 3, /*LOAD $0 #3; */
+            1, 1, 0, 5, /*LOAD $1 #5; */
+            69, 0, 1, 2, /*SEQ $0 $1 $2; */
+            70, 0, 1, 3, /*SNEQ $0 $1 $3; */
+            71, 0, 1, 4, /*SLT $0 $1 $4; */
+            72, 0, 0, 5, /*SLTE $0 $0 $5; */
+            73, 0, 1, 6, /*SGT $0 $1 $6; */
+            74, 1, 0, 7, /*SGTE $1 $0 $7; */
+        ];
+        vm.registers[2] = 9;
+        vm.run().unwrap();
+        assert_eq!(vm.registers[2..8], [0, 1, 1, 1, 0, 1]);
+        assert_eq!(vm.comparison_flag, false);
+    }
+
+    #[test]
+    fn should_set_register_on_float_condition() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            22, 0, 0, 1, /*LOADF64 $0 #1; */
+            22, 1, 0, 2, /*LOADF64 $1 #2; */
+            75, 0, 0, 0, /*SEQF64 $0 $0 $0; */
+            76, 0, 1, 1, /*SNEQF64 $0 $1 $1; */
+            77, 1, 0, 2, /*SLTF64 $1 $0 $2; */
+            78, 0, 1, 3, /*SLTEF64 $0 $1 $3; */
+            79, 1, 0, 4, /*SGTF64 $1 $0 $4; */
+            80, 0, 1, 5, /*SGTEF64 $0 $1 $5; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.registers[0..6], [1, 1, 0, 1, 1, 0]);
+    }
+
+    #[test]
+    fn should_add_and_sub_immediate() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            1, 0, 0, 10, /*LOAD $0 #10; */
+            81, 0, 255, 253, 1, /*ADDI $0 #-3 $1; */
+            82, 0, 0, 20, 2, /*SUBI $0 #20 $2; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.registers[1], 7);
+        assert_eq!(vm.registers[2], -10);
+    }
+
+    #[test]
+    fn should_cmp_immediate_and_jump_to_address() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            1, 0, 0, 3, /*LOAD $0 #3; */
+            83, 0, 0, 3, /*CMPI $0 #3; */
+            85, 0, 0, 0, 18, /*JEI #18; */
+            1, 1, 0, 1, /*LOAD $1 #1; */
+            0, /*HLT; */
+            86, 0, 0, 0, 28, /*JNEI #28; */
+            84, 0, 0, 0, 33, /*JMPI #33; */
+            1, 2, 0, 1, /*LOAD $2 #1; */
+            0, /*HLT; */
+            1, 3, 0, 1, /*LOAD $3 #1; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.registers[1..4], [0, 0, 1]);
+    }
+
+    #[test]
+    fn should_trap_overflow_on_add_immediate() {
+        let mut vm = VM::new();
+        vm.set_overflow_mode(Trapping);
+        vm.program = vec![
+            48, 0, 127, 255, 255, 255, /*LOAD32 $0 #2147483647; */
+            81, 0, 0, 1, 1, /*ADDI $0 #1 $1; */
+        ];
+        assert_eq!(vm.run(), Err(IntegerOverflow { pc: 6 }));
+    }
+
+    fn run_assembly(source: &str) -> VM {
+        let mut assembler = Assembler::new();
+        let program = assembler.process(source).unwrap();
+        let mut vm = VM::new();
+        vm.load_program(program).unwrap();
+        vm.run().unwrap();
+        return vm;
+    }
+
+    #[test]
+    fn should_compute_factorial_recursively() {
+        let vm = run_assembly(&factorial_source(1));
+        assert_eq!(vm.registers[0], 1);
+
+        let vm = run_assembly(&factorial_source(5));
+        assert_eq!(vm.registers[0], 120);
+        assert_eq!(vm.stack.len(), 0);
+        assert_eq!(vm.sp, 0);
+        assert_eq!(vm.bp, 0);
+
+        let vm = run_assembly(&factorial_source(10));
+        assert_eq!(vm.registers[0], 3628800);
+    }
+
+    #[test]
+    fn should_load_and_store_locals_relative_to_bp() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            1, 0, 0, 7, /*LOAD $0 #7; */
+            37, 0, /*PUSH $0; */
+            1, 1, 0, 13, /*LOAD $1 #13; */
+            39, 1, /*CALL $1; */
+            0, /*HLT; */
+            37, 0, /*PUSH $0; */
+            87, 2, 255, 253, /*LOADLOCAL $2 #-3; */
+            2, 2, 2, 3, /*ADD $2 $2 $3; */
+            88, 3, 0, 0, /*STORELOCAL $3 #0; */
+            87, 4, 0, 0, /*LOADLOCAL $4 #0; */
+            88, 4, 255, 253, /*STORELOCAL $4 #-3; */
+            40,  /*RET; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.registers[2], 7);
+        assert_eq!(vm.registers[4], 14);
+        assert_eq!(vm.stack, vec![14]);
+        assert_eq!(vm.sp, 1);
+        assert_eq!(vm.bp, 0);
+    }
+
+    #[test]
+    fn should_fault_on_stack_slot_out_of_bounds() {
+        let mut vm = VM::new();
+        vm.program = vec![87, 0, 0, 0]; /*LOADLOCAL $0 #0; */
+        assert_eq!(vm.run(), Err(StackSlotOutOfBounds { offset: 0, pc: 0 }));
+
+        let mut vm = VM::new();
+        vm.program = vec![
+            1, 0, 0, 6, /*LOAD $0 #6; */
+            39, 0, /*CALL $0; */
+            88, 0, 255, 252, /*STORELOCAL $0 #-4; */
+        ];
+        assert_eq!(vm.run(), Err(StackSlotOutOfBounds { offset: -4, pc: 6 }));
+    }
+
+    #[test]
+    fn should_overflow_configured_stack_size_on_recursion() {
+        let mut vm = VM::new();
+        vm.set_stack_size(64).unwrap();
+        vm.program = vec![
+            1, 0, 0, 4, /*LOAD $0 #4; */
+            37, 0, /*PUSH $0; */
+            39, 0, /*CALL $0; */
+        ];
+        assert_eq!(vm.run(), Err(StackOverflow { depth: 21, pc: 6 }));
+        assert_eq!(vm.state(), Faulted);
+        assert_eq!(vm.call_depth(), 21);
+        assert_eq!(vm.stack.len(), 64);
+    }
+
+    #[test]
+    fn should_overflow_configured_stack_size_on_push() {
+        let mut vm = VM::new();
+        vm.set_stack_size(2).unwrap();
+        vm.program = vec![
+            37, 0, /*PUSH $0; */
+            37, 0, /*PUSH $0; */
+            37, 0, /*PUSH $0; */
+        ];
+        assert_eq!(vm.run(), Err(StackOverflow { depth: 0, pc: 4 }));
+        assert_eq!(vm.stack.len(), 2);
+    }
+
+    #[test]
+    fn should_not_shrink_stack_size_below_stack() {
+        let mut vm = VM::new();
+        vm.stack = vec![1, 2, 3];
+        assert!(vm.set_stack_size(2).is_err());
+        assert_eq!(vm.stack_size(), DEFAULT_STACK_SIZE);
+    }
+
+    #[test]
+    fn should_underflow_when_pop_into_caller_frame() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            1, 0, 0, 10, /*LOAD $0 #10; */
+            37, 0, /*PUSH $0; */
+            39, 0, /*CALL $0; */
+            0, 0, /*HLT; HLT; */
+            38, 1, /*POP $1; */
+        ];
+        assert_eq!(vm.run(), Err(StackUnderflow { depth: 1, pc: 10 }));
+        assert_eq!(vm.stack, vec![10, 8, 0]);
+    }
+
+    #[test]
+    fn should_call_immediate_address() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            89, 0, 0, 0, 6, /*CALLI #6; */
+            0, /*HLT; */
+            1, 0, 0, 42, /*LOAD $0 #42; */
+            40, /*RET; */
+        ];
+        vm.run().unwrap();
+        assert_eq!(vm.registers[0], 42);
+        assert_eq!(vm.call_depth(), 0);
+    }
+
+    #[test]
+    fn should_fault_when_call_target_is_not_an_instruction() {
+        let mut vm = VM::new();
+        vm.program = vec![
+            89, 0, 0, 0, 7, /*CALLI #7; */
+            0, /*HLT; */
+            1, 0, 0, 42, /*LOAD $0 #42; */
+            40, /*RET; */
+        ];
+        assert_eq!(vm.run(), Err(InvalidCallTarget { address: 7, pc: 0 }));
+
+        let mut vm = VM::new();
+        vm.program = vec![
+            1, 1, 0, 100, /*LOAD $1 #100; */
+            39, 1, /*CALL $1; */
+        ];
+        assert_eq!(
+            vm.run(),
+            Err(InvalidCallTarget {
+                address: 100,
+                pc: 4
+            })
+        );
+    }
+
+    #[test]
+    fn should_load_word_from_ro_data() {
+        let mut vm = VM::new();
+        vm.ro_data = vec![9, 0, 0, 1, 2];
+        vm.program = vec![
+            1, 1, 0, 1, /*LOAD $1 #1; */
+            90, 0, 1, 0, 0, /*LDROW $0 $1 #0; */
+            90, 2, 1, 0, 1, /*LDROW $2 $1 #1; */
+        ];
+        assert_eq!(vm.run(), Err(BadRoDataOffset { offset: 2, pc: 9 }));
+        assert_eq!(vm.registers[0], 258);
+    }
+
+    #[test]
+    fn should_switch_through_jump_table() {
+        let source = |case: i32| {
+            format!(
+                ".code\n\
+                 load $1 #{}\n\
+                 load $2 #4\n\
+                 mul $1 $2 $1\n\
+                 ldrow $3 $1 @cases\n\
+                 call $3\n\
+                 hlt\n\
+                 zero: load $0 #100\n\
+                 ret\n\
+                 one: load $0 #200\n\
+                 ret\n\
+                 two: load $0 #300\n\
+                 ret\n\
+                 .data\n\
+                 cases: .table @zero @one @two",
+                case
+            )
+        };
+        assert_eq!(run_assembly(&source(0)).registers[0], 100);
+        assert_eq!(run_assembly(&source(1)).registers[0], 200);
+        assert_eq!(run_assembly(&source(2)).registers[0], 300);
+    }
+
+    #[test]
+    fn should_call_registered_host_function() {
+        let mut vm = VM::new();
+        vm.register_syscall(
+            100,
+            "double",
+            Rc::new(|vm: &mut VM| {
+                vm.registers[0] *= 2;
+                Ok(())
+            }),
+        );
+        vm.program = vec![1, 0, 0, 21, 91, 0, 100, 0];
+        assert_eq!(vm.run(), Ok(Halted(0)));
+        assert_eq!(vm.registers[0], 42);
+        assert_eq!(vm.syscalls().get(100).unwrap().name, "double");
+    }
+
+    #[test]
+    fn should_exit_with_code_when_syscall_exit() {
+        let mut vm = VM::new();
+        vm.program = vec![1, 0, 0, 7, 91, 0, SYS_EXIT as u8, 1, 1, 0, 1, 0];
+        assert_eq!(vm.run(), Ok(Halted(7)));
+        assert_eq!(vm.exit_code(), Some(7));
+        assert_eq!(vm.registers[1], 0);
+    }
+
+    #[test]
+    fn should_not_exit_on_syscall_after_resuming_from_halt() {
+        let mut vm = VM::new();
+        vm.set_exit_banner(false);
+        vm.register_syscall(
+            100,
+            "increment",
+            Rc::new(|vm: &mut VM| {
+                vm.registers[0] += 1;
+                Ok(())
+            }),
+        );
+        /* HLT; SYSCALL #100; LOAD $1 #5; HLT */
+        vm.program = vec![0, 91, 0, 100, 1, 1, 0, 5, 0];
+        assert_eq!(vm.run(), Ok(Halted(0)));
+        assert_eq!(vm.run(), Ok(Halted(0)));
+        assert_eq!(vm.registers[0], 1);
+        assert_eq!(vm.registers[1], 5);
+        assert_eq!(vm.pc, 9);
+    }
+
+    #[test]
+    fn should_read_time_when_syscall_time() {
+        let mut vm = VM::new();
+        vm.program = vec![91, 0, SYS_TIME as u8, 0];
+        vm.run().unwrap();
+        assert!(vm.registers[0] > 0);
+        assert!(vm.registers[1] >= 0 && vm.registers[1] < 1000);
+    }
+
+    #[test]
+    fn should_fail_when_syscall_write_heap_out_of_bounds() {
+        let mut vm = VM::new();
+        vm.heap = vec![0; 8];
+        vm.program = vec![1, 0, 0, 4, 1, 1, 0, 8, 91, 0, 5, 0];
+        assert_eq!(
+            vm.run(),
+            Err(SyscallFailed {
+                number: 5,
+                message: "heap range 4..12 out of bounds".to_string(),
+                pc: 8
+            })
+        );
+    }
+
+    #[test]
+    fn should_fail_when_syscall_unknown() {
+        let mut vm = VM::new();
+        vm.program = vec![91, 0, 42, 0];
+        assert_eq!(vm.run(), Err(UnknownSyscall { number: 42, pc: 0 }));
+    }
+
+    #[test]
+    fn should_write_prts_and_exit_banner_to_output() {
+        let output = SharedBuffer::new();
+        let mut vm = VM::new();
+        vm.set_output(Box::new(output.clone()));
+        vm.ro_data = "hello\0".as_bytes().to_vec();
+        vm.program = vec![1, 0, 0, 0, 14, 0, 0];
+        vm.run().unwrap();
+        assert_eq!(output.text(), "hello\nexit(0)\n");
+    }
+
+    #[test]
+    fn should_not_write_exit_banner_when_disabled() {
+        let output = SharedBuffer::new();
+        let mut vm = VM::new();
+        vm.set_output(Box::new(output.clone()));
+        vm.set_exit_banner(false);
+        vm.program = vec![1, 0, 0, 42, 91, 0, SYS_PRINT_INT as u8, 0];
+        vm.run().unwrap();
+        assert_eq!(output.text(), "42");
+    }
+
+    #[test]
+    fn should_read_line_from_input() {
+        let mut vm = VM::new();
+        vm.set_input(Box::new(Cursor::new("du\nrest".as_bytes().to_vec())));
+        vm.heap = vec![0; 8];
+        vm.program = vec![1, 0, 0, 2, 1, 1, 0, 4, 91, 0, SYS_READ_LINE as u8, 0];
+        vm.run().unwrap();
+        assert_eq!(vm.registers[0], 2);
+        assert_eq!(vm.heap, vec![0, 0, 100, 117, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn should_halt_with_status_register() {
+        let output = SharedBuffer::new();
+        let mut vm = VM::new();
+        vm.set_output(Box::new(output.clone()));
+        vm.program = vec![1, 3, 0, 2, 92, 3];
+        assert_eq!(vm.run(), Ok(Halted(2)));
+        assert_eq!(vm.state(), Halted(2));
+        assert_eq!(vm.exit_code(), Some(2));
+        assert_eq!(output.text(), "\nexit(2)\n");
+    }
+
+    #[test]
+    fn should_halt_with_assembled_status() {
+        let vm = run_assembly(".data\n.code\nload $1 #3\nhlt $1");
+        assert_eq!(vm.state(), Halted(3));
+        let vm = run_assembly(".data\n.code\nload $1 #3\nhlt");
+        assert_eq!(vm.state(), Halted(0));
+    }
 }
@@ -0,0 +1,210 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+use crate::vm::heap_allocator::HeapAllocator;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/* header in front of every gc block: 4 bytes payload size, 1 byte mark, 3 bytes padding */
+pub const GC_HEADER_SIZE: usize = 8;
+const GC_MARK_OFFSET: usize = 4;
+pub const DEFAULT_GC_THRESHOLD: usize = 65536;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct GcStats {
+    pub collections: u64,
+    pub reclaimed_blocks: usize,
+    pub reclaimed_bytes: usize,
+    pub total_reclaimed_bytes: u64,
+    pub live_blocks: usize,
+    pub live_bytes: usize,
+    pub threshold: usize,
+}
+
+/* conservative mark-and-sweep collector over the blocks of a HeapAllocator */
+#[derive(Debug, Clone)]
+pub struct GarbageCollector {
+    pub(crate) enabled: bool,
+    threshold: usize,
+    stats: GcStats,
+}
+
+impl GarbageCollector {
+    pub fn new() -> GarbageCollector {
+        GarbageCollector {
+            enabled: false,
+            threshold: DEFAULT_GC_THRESHOLD,
+            stats: GcStats {
+                collections: 0,
+                reclaimed_blocks: 0,
+                reclaimed_bytes: 0,
+                total_reclaimed_bytes: 0,
+                live_blocks: 0,
+                live_bytes: 0,
+                threshold: DEFAULT_GC_THRESHOLD,
+            },
+        }
+    }
+
+    pub fn enable(&mut self, threshold: usize) {
+        self.enabled = true;
+        self.threshold = threshold;
+        self.stats.threshold = threshold;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        return self.enabled;
+    }
+
+    pub fn threshold(&self) -> usize {
+        return self.threshold;
+    }
+
+    pub(crate) fn set_threshold(&mut self, threshold: usize) {
+        self.threshold = threshold;
+        self.stats.threshold = threshold;
+    }
+
+    pub fn stats(&self) -> GcStats {
+        return self.stats.clone();
+    }
+
+    pub fn should_collect(&self, used_bytes: usize) -> bool {
+        return self.enabled && used_bytes > self.threshold;
+    }
+
+    /* allocate a block with a header, returns the payload address */
+    pub fn allocate(
+        &mut self,
+        heap: &mut Vec<u8>,
+        allocator: &mut HeapAllocator,
+        size: usize,
+    ) -> usize {
+        /* a zero size payload still gets a byte, so its address is inside the block */
+        let offset = allocator.allocate(heap, size.max(1) + GC_HEADER_SIZE);
+        heap[offset..offset + 4].copy_from_slice(&(size as u32).to_be_bytes());
+        heap[offset + GC_MARK_OFFSET] = 0;
+        return offset + GC_HEADER_SIZE;
+    }
+
+    /* explicit free of a payload address returned by allocate */
+    pub fn free(&mut self, allocator: &mut HeapAllocator, address: usize) -> bool {
+        if address < GC_HEADER_SIZE {
+            return false;
+        }
+        return allocator.free(address - GC_HEADER_SIZE);
+    }
+
+    /* offset and end of the allocated block whose payload contains `value` */
+    fn find_block(allocator: &HeapAllocator, value: i32) -> Option<(usize, usize)> {
+        if value < GC_HEADER_SIZE as i32 {
+            return None;
+        }
+        let address = value as usize;
+        let blocks = allocator.blocks();
+        let index = match blocks.binary_search_by(|block| block.offset.cmp(&address)) {
+            Ok(index) => index,
+            Err(0) => return None,
+            Err(index) => index - 1,
+        };
+        let block = &blocks[index];
+        if block.free
+            || address < block.offset + GC_HEADER_SIZE
+            || address >= block.offset + block.size
+        {
+            return None;
+        }
+        return Some((block.offset, block.offset + block.size));
+    }
+
+    pub fn collect(
+        &mut self,
+        heap: &mut Vec<u8>,
+        allocator: &mut HeapAllocator,
+        roots: &[i32],
+    ) -> GcStats {
+        for block in allocator.blocks() {
+            if !block.free {
+                heap[block.offset + GC_MARK_OFFSET] = 0;
+            }
+        }
+
+        // mark: anything that looks like a pointer into a payload keeps the block alive
+        let mut work_list: Vec<(usize, usize)> = Vec::new();
+        for root in roots {
+            if let Some((offset, end)) = GarbageCollector::find_block(allocator, *root) {
+                if heap[offset + GC_MARK_OFFSET] == 0 {
+                    heap[offset + GC_MARK_OFFSET] = 1;
+                    work_list.push((offset, end));
+                }
+            }
+        }
+        while let Some((offset, end)) = work_list.pop() {
+            // the size in the header is program writable, the allocator's block is not
+            let payload_end = end.min(heap.len());
+            let mut word = offset + GC_HEADER_SIZE;
+            while word + 4 <= payload_end {
+                let mut value_bytes = [0; 4];
+                value_bytes.copy_from_slice(&heap[word..word + 4]);
+                let value = i32::from_be_bytes(value_bytes);
+                if let Some((child, child_end)) = GarbageCollector::find_block(allocator, value) {
+                    if heap[child + GC_MARK_OFFSET] == 0 {
+                        heap[child + GC_MARK_OFFSET] = 1;
+                        work_list.push((child, child_end));
+                    }
+                }
+                word += 4;
+            }
+        }
+
+        // sweep
+        let mut garbage: Vec<(usize, usize)> = Vec::new();
+        let mut live_blocks = 0;
+        let mut live_bytes = 0;
+        for block in allocator.blocks() {
+            if block.free {
+                continue;
+            }
+            if heap[block.offset + GC_MARK_OFFSET] == 0 {
+                garbage.push((block.offset, block.size));
+            } else {
+                live_blocks += 1;
+                live_bytes += block.size;
+            }
+        }
+        let mut reclaimed_bytes = 0;
+        for (offset, size) in &garbage {
+            allocator.free(*offset);
+            reclaimed_bytes += size;
+        }
+
+        // leave room to grow so a large live set does not collect on every allocation
+        if live_bytes * 2 > self.threshold {
+            self.threshold = live_bytes * 2;
+        }
+
+        self.stats.collections += 1;
+        self.stats.reclaimed_blocks = garbage.len();
+        self.stats.reclaimed_bytes = reclaimed_bytes;
+        self.stats.total_reclaimed_bytes += reclaimed_bytes as u64;
+        self.stats.live_blocks = live_blocks;
+        self.stats.live_bytes = live_bytes;
+        self.stats.threshold = self.threshold;
+        return self.stats();
+    }
+}
+
+impl Display for GcStats {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&format!(
+            "collections: {}, reclaimed: {} bytes in {} blocks, live: {} bytes in {} blocks, threshold: {} bytes",
+            self.collections,
+            self.reclaimed_bytes,
+            self.reclaimed_blocks,
+            self.live_bytes,
+            self.live_blocks,
+            self.threshold
+        ))
+    }
+}
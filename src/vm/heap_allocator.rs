@@ -0,0 +1,188 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/* block sizes are rounded up to this, so every block can hold an f64 */
+pub const HEAP_ALIGNMENT: usize = 8;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct HeapBlock {
+    pub offset: usize,
+    pub size: usize,
+    pub free: bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct HeapStats {
+    pub heap_size: usize,
+    pub used_bytes: usize,
+    pub free_bytes: usize,
+    pub used_blocks: usize,
+    pub free_blocks: usize,
+    pub allocations: u64,
+    pub frees: u64,
+}
+
+/* first-fit free list over VM::heap, blocks are kept sorted by offset */
+#[derive(Debug, Clone)]
+pub struct HeapAllocator {
+    pub(crate) blocks: Vec<HeapBlock>,
+    allocations: u64,
+    frees: u64,
+}
+
+impl HeapAllocator {
+    pub fn new() -> HeapAllocator {
+        HeapAllocator {
+            blocks: Vec::new(),
+            allocations: 0,
+            frees: 0,
+        }
+    }
+
+    /* rebuild an allocator from saved blocks, see vm::snapshot */
+    pub(crate) fn restore(blocks: Vec<HeapBlock>, allocations: u64, frees: u64) -> HeapAllocator {
+        HeapAllocator {
+            blocks,
+            allocations,
+            frees,
+        }
+    }
+
+    pub fn allocations(&self) -> u64 {
+        return self.allocations;
+    }
+
+    pub fn frees(&self) -> u64 {
+        return self.frees;
+    }
+
+    fn align(size: usize) -> usize {
+        if size == 0 {
+            return HEAP_ALIGNMENT;
+        }
+        return (size + HEAP_ALIGNMENT - 1) / HEAP_ALIGNMENT * HEAP_ALIGNMENT;
+    }
+
+    /* returns the base address of a zeroed block of at least `size` bytes */
+    pub fn allocate(&mut self, heap: &mut Vec<u8>, size: usize) -> usize {
+        let size = HeapAllocator::align(size);
+        self.allocations += 1;
+
+        for index in 0..self.blocks.len() {
+            if self.blocks[index].free && self.blocks[index].size >= size {
+                let offset = self.blocks[index].offset;
+                let remaining = self.blocks[index].size - size;
+                if remaining > 0 {
+                    self.blocks.insert(
+                        index + 1,
+                        HeapBlock {
+                            offset: offset + size,
+                            size: remaining,
+                            free: true,
+                        },
+                    );
+                }
+                self.blocks[index].size = size;
+                self.blocks[index].free = false;
+                for byte in &mut heap[offset..offset + size] {
+                    *byte = 0;
+                }
+                return offset;
+            }
+        }
+
+        let offset = heap.len();
+        heap.resize(offset + size, 0);
+        self.blocks.push(HeapBlock {
+            offset,
+            size,
+            free: false,
+        });
+        return offset;
+    }
+
+    /* returns false when `offset` is not the base of an allocated block */
+    pub fn free(&mut self, offset: usize) -> bool {
+        let found = self
+            .blocks
+            .iter()
+            .position(|block| block.offset == offset && !block.free);
+        match found {
+            Some(index) => {
+                self.blocks[index].free = true;
+                self.frees += 1;
+                self.coalesce(index);
+                return true;
+            }
+            None => {
+                return false;
+            }
+        }
+    }
+
+    /* merge the free block at `index` with its free neighbours */
+    fn coalesce(&mut self, index: usize) {
+        let mut index = index;
+        if index + 1 < self.blocks.len() && self.blocks[index + 1].free {
+            let next = self.blocks.remove(index + 1);
+            self.blocks[index].size += next.size;
+        }
+        if index > 0 && self.blocks[index - 1].free {
+            let current = self.blocks.remove(index);
+            index -= 1;
+            self.blocks[index].size += current.size;
+        }
+    }
+
+    pub fn block_at(&self, offset: usize) -> Option<&HeapBlock> {
+        return self
+            .blocks
+            .iter()
+            .find(|block| block.offset == offset && !block.free);
+    }
+
+    pub fn blocks(&self) -> &Vec<HeapBlock> {
+        return &self.blocks;
+    }
+
+    pub fn stats(&self, heap_size: usize) -> HeapStats {
+        let mut stats = HeapStats {
+            heap_size,
+            used_bytes: 0,
+            free_bytes: 0,
+            used_blocks: 0,
+            free_blocks: 0,
+            allocations: self.allocations,
+            frees: self.frees,
+        };
+        for block in &self.blocks {
+            if block.free {
+                stats.free_bytes += block.size;
+                stats.free_blocks += 1;
+            } else {
+                stats.used_bytes += block.size;
+                stats.used_blocks += 1;
+            }
+        }
+        return stats;
+    }
+}
+
+impl Display for HeapStats {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&format!(
+            "heap: {} bytes, used: {} bytes in {} blocks, free: {} bytes in {} blocks, allocations: {}, frees: {}",
+            self.heap_size,
+            self.used_bytes,
+            self.used_blocks,
+            self.free_bytes,
+            self.free_blocks,
+            self.allocations,
+            self.frees
+        ))
+    }
+}
@@ -1,11 +1,37 @@
 use crate::vm::instruction::OpCode::{
-    ADD, ADDF64, ALOC, AND, CALL, DEC, DIV, DIVF64, EQ, EQF64, GT, GTE, GTEF64, GTF64, HLT, IGL,
-    INC, JE, JG, JL, JMP, JMPB, JMPF, JNE, LOAD, LOADF64, LT, LTE, LTEF64, LTF64, MUL, MULF64,
-    NEQF64, NOT, OR, POP, PRTS, PUSH, RET, SUB, SUBF64, XOR,
+    ABSF64, ADD, ADDCHK, ADDF64, ADDI, ADDWRAP, ALOC, AND, CALL, CALLI, CMPF64, CMPI, DEC, DIV,
+    DIVF64, DIVU, EQ, EQF64, FREE, FTOI, GT, GTE, GTEF64, GTF64, HLT, HLTR, IGL, INC, ITOF, JE,
+    JEI, JG, JL, JMP, JMPB, JMPF, JMPI, JNE, JNEI, LDB, LDF64, LDROW, LDW, LOAD, LOAD32, LOADF64,
+    LOADLOCAL, LOADREM, LT, LTE, LTEF64, LTF64, MOD, MODU, MOVF64, MUL, MULCHK, MULF64, MULWRAP,
+    NEGF64, NEQF64, NOT, OR, POP, PRTS, PUSH, RET, SAR, SEQ, SEQF64, SGT, SGTE, SGTEF64, SGTF64,
+    SHL, SHR, SLT, SLTE, SLTEF64, SLTF64, SNEQ, SNEQF64, SQRTF64, STB, STF64, STORELOCAL, STW, SUB,
+    SUBCHK, SUBF64, SUBI, SUBWRAP, SYSCALL, XOR,
 };
+use crate::vm::instruction::OperandKind::{Immediate16, Immediate32, Register};
 use std::fmt;
 use std::fmt::{Display, Formatter};
 
+/* how the operand bytes after an opcode are read */
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum OperandKind {
+    /* 1 byte, an int or float register number */
+    Register,
+    /* 2 bytes big-endian */
+    Immediate16,
+    /* 4 bytes big-endian, an absolute address for JMPI / JEI / JNEI / CALLI */
+    Immediate32,
+}
+
+impl OperandKind {
+    pub fn length(&self) -> usize {
+        match *self {
+            Register => 1,
+            Immediate16 => 2,
+            Immediate32 => 4,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum OpCode {
     HLT = 0,
@@ -56,7 +82,83 @@ pub enum OpCode {
     CALL = 39,
     RET = 40,
 
-    IGL = 41,
+    /* heap memory, address is base register plus immediate offset */
+    LDB = 41,
+    LDW = 42,
+    LDF64 = 43,
+    STB = 44,
+    STW = 45,
+    STF64 = 46,
+
+    FREE = 47,
+
+    /* LOAD with a full 32 bits immediate */
+    LOAD32 = 48,
+
+    /* int / float conversion and float register operations */
+    ITOF = 49,
+    FTOI = 50,
+    MOVF64 = 51,
+    NEGF64 = 52,
+    ABSF64 = 53,
+    SQRTF64 = 54,
+    CMPF64 = 55,
+
+    /* shifts, modulo, unsigned and explicitly wrapping / checked arithmetic */
+    SHL = 56,
+    SHR = 57,
+    SAR = 58,
+    MOD = 59,
+    DIVU = 60,
+    MODU = 61,
+    LOADREM = 62,
+    ADDWRAP = 63,
+    SUBWRAP = 64,
+    MULWRAP = 65,
+    ADDCHK = 66,
+    SUBCHK = 67,
+    MULCHK = 68,
+
+    /* set on condition, write 1 or 0 into an int register */
+    SEQ = 69,
+    SNEQ = 70,
+    SLT = 71,
+    SLTE = 72,
+    SGT = 73,
+    SGTE = 74,
+    SEQF64 = 75,
+    SNEQF64 = 76,
+    SLTF64 = 77,
+    SLTEF64 = 78,
+    SGTF64 = 79,
+    SGTEF64 = 80,
+
+    /* reg, 16 bits signed immediate, regTarget */
+    ADDI = 81,
+    SUBI = 82,
+    /* reg, 16 bits signed immediate, sets comparison_flag when equal */
+    CMPI = 83,
+    /* 32 bits absolute address */
+    JMPI = 84,
+    JEI = 85,
+    JNEI = 86,
+
+    /* reg, 16 bits signed slot offset from bp */
+    LOADLOCAL = 87,
+    STORELOCAL = 88,
+
+    /* 32 bits absolute address */
+    CALLI = 89,
+    /* regTarget regBase offsetH offsetL, reads a word from ro_data */
+    LDROW = 90,
+
+    /* 16 bits syscall number, see vm::syscall */
+    SYSCALL = 91,
+
+    /* HLTR $status, halt with the register as exit status */
+    HLTR = 92,
+
+    IGL = 255,
 }
 
 impl Display for OpCode {
@@ -121,11 +223,138 @@ impl From<u8> for OpCode {
             39 => return CALL,
             40 => return RET,
 
+            41 => return LDB,
+            42 => return LDW,
+            43 => return LDF64,
+            44 => return STB,
+            45 => return STW,
+            46 => return STF64,
+
+            47 => return FREE,
+
+            48 => return LOAD32,
+
+            49 => return ITOF,
+            50 => return FTOI,
+            51 => return MOVF64,
+            52 => return NEGF64,
+            53 => return ABSF64,
+            54 => return SQRTF64,
+            55 => return CMPF64,
+
+            56 => return SHL,
+            57 => return SHR,
+            58 => return SAR,
+            59 => return MOD,
+            60 => return DIVU,
+            61 => return MODU,
+            62 => return LOADREM,
+            63 => return ADDWRAP,
+            64 => return SUBWRAP,
+            65 => return MULWRAP,
+            66 => return ADDCHK,
+            67 => return SUBCHK,
+            68 => return MULCHK,
+
+            69 => return SEQ,
+            70 => return SNEQ,
+            71 => return SLT,
+            72 => return SLTE,
+            73 => return SGT,
+            74 => return SGTE,
+            75 => return SEQF64,
+            76 => return SNEQF64,
+            77 => return SLTF64,
+            78 => return SLTEF64,
+            79 => return SGTF64,
+            80 => return SGTEF64,
+
+            81 => return ADDI,
+            82 => return SUBI,
+            83 => return CMPI,
+            84 => return JMPI,
+            85 => return JEI,
+            86 => return JNEI,
+
+            87 => return LOADLOCAL,
+            88 => return STORELOCAL,
+
+            89 => return CALLI,
+            90 => return LDROW,
+
+            91 => return SYSCALL,
+
+            92 => return HLTR,
+
             _ => return IGL,
         }
     }
 }
 
+impl OpCode {
+    /* number of bytes following the opcode byte */
+    pub fn operand_length(&self) -> usize {
+        match *self {
+            HLT | RET | IGL => {
+                return 0;
+            }
+            JMP | JMPF | JMPB | JE | JNE | JL | JG | INC | DEC | PRTS | PUSH | POP | CALL
+            | FREE | LOADREM | HLTR => {
+                return 1;
+            }
+            EQ | LT | LTE | GT | GTE | ALOC | EQF64 | NEQF64 | GTF64 | GTEF64 | LTF64 | LTEF64
+            | NOT | ITOF | FTOI | MOVF64 | NEGF64 | ABSF64 | SQRTF64 | SYSCALL => {
+                return 2;
+            }
+            LOAD | ADD | SUB | MUL | DIV | LOADF64 | ADDF64 | SUBF64 | MULF64 | DIVF64 | AND
+            | OR | XOR | CMPF64 | SHL | SHR | SAR | MOD | DIVU | MODU | ADDWRAP | SUBWRAP
+            | MULWRAP | ADDCHK | SUBCHK | MULCHK | SEQ | SNEQ | SLT | SLTE | SGT | SGTE
+            | SEQF64 | SNEQF64 | SLTF64 | SLTEF64 | SGTF64 | SGTEF64 | CMPI | LOADLOCAL
+            | STORELOCAL => {
+                return 3;
+            }
+            LDB | LDW | LDF64 | STB | STW | STF64 | ADDI | SUBI | JMPI | JEI | JNEI | CALLI
+            | LDROW => {
+                return 4;
+            }
+            LOAD32 => {
+                return 5;
+            }
+        }
+    }
+}
+
+impl OpCode {
+    /* the operands in encoding order, their lengths add up to operand_length */
+    pub fn operands(&self) -> &'static [OperandKind] {
+        match *self {
+            HLT | RET | IGL => &[],
+            JMP | JMPF | JMPB | JE | JNE | JL | JG | INC | DEC | PRTS | PUSH | POP | CALL
+            | FREE | LOADREM | HLTR => &[Register],
+            EQ | LT | LTE | GT | GTE | ALOC | EQF64 | NEQF64 | GTF64 | GTEF64 | LTF64 | LTEF64
+            | NOT | ITOF | FTOI | MOVF64 | NEGF64 | ABSF64 | SQRTF64 => &[Register, Register],
+            SYSCALL => &[Immediate16],
+            LOAD | LOADF64 | CMPI | LOADLOCAL | STORELOCAL => &[Register, Immediate16],
+            ADD | SUB | MUL | DIV | ADDF64 | SUBF64 | MULF64 | DIVF64 | AND | OR | XOR | CMPF64
+            | SHL | SHR | SAR | MOD | DIVU | MODU | ADDWRAP | SUBWRAP | MULWRAP | ADDCHK
+            | SUBCHK | MULCHK | SEQ | SNEQ | SLT | SLTE | SGT | SGTE | SEQF64 | SNEQF64
+            | SLTF64 | SLTEF64 | SGTF64 | SGTEF64 => &[Register, Register, Register],
+            LDB | LDW | LDF64 | STB | STW | STF64 | LDROW => &[Register, Register, Immediate16],
+            ADDI | SUBI => &[Register, Immediate16, Register],
+            JMPI | JEI | JNEI | CALLI => &[Immediate32],
+            LOAD32 => &[Register, Immediate32],
+        }
+    }
+
+    /* immediates the vm sign extends, the others are read unsigned */
+    pub fn has_signed_immediate(&self) -> bool {
+        match *self {
+            ADDI | SUBI | CMPI | LOADLOCAL | STORELOCAL | LOAD32 => true,
+            _ => false,
+        }
+    }
+}
+
 impl Instruction {
     pub fn new(opcode: OpCode) -> Instruction {
         Instruction { opcode }
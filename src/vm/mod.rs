@@ -1,4 +1,15 @@
+pub mod gc;
+pub mod heap_allocator;
 pub mod instruction;
+pub mod overflow_mode;
+pub mod profiler;
+pub mod snapshot;
+pub mod syscall;
+pub mod trace;
+pub mod verifier;
 pub mod vm;
+pub mod vm_error;
+pub mod vm_io;
+pub mod vm_state;
 
 pub mod __tests__;
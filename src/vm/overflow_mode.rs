@@ -0,0 +1,14 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+/* how ADD, SUB, MUL, INC and DEC treat a result outside of i32 */
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum OverflowMode {
+    /* two's complement wrap around, the default */
+    Wrapping,
+    /* clamp to i32::MIN or i32::MAX */
+    Saturating,
+    /* fault with VmError::IntegerOverflow */
+    Trapping,
+}
@@ -0,0 +1,174 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+use crate::vm::instruction::OpCode;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/* rows of each table printed by the report */
+const REPORT_ROWS: usize = 10;
+const NO_LABEL: &str = "<no label>";
+
+/* execution counts per opcode, per pc and per CALL target */
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    opcode_counts: Vec<u64>,
+    pc_counts: Vec<u64>,
+    call_counts: Vec<(usize, u64)>,
+    total: u64,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProfileReport {
+    pub total: u64,
+    /* instructions executed under each label, hottest first */
+    pub labels: Vec<(String, u64)>,
+    /* instruction mix, most executed first */
+    pub opcodes: Vec<(OpCode, u64)>,
+    /* CALL counts per target label, most called first */
+    pub calls: Vec<(String, u64)>,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler {
+            opcode_counts: vec![0; 256],
+            pc_counts: Vec::new(),
+            call_counts: Vec::new(),
+            total: 0,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        return self.total;
+    }
+
+    pub fn pc_count(&self, pc: usize) -> u64 {
+        return self.pc_counts.get(pc).map_or(0, |count| *count);
+    }
+
+    pub fn opcode_count(&self, opcode: OpCode) -> u64 {
+        return self.opcode_counts[opcode as usize];
+    }
+
+    pub fn call_count(&self, target: usize) -> u64 {
+        return self
+            .call_counts
+            .iter()
+            .find(|(address, _)| *address == target)
+            .map_or(0, |(_, count)| *count);
+    }
+
+    pub(crate) fn record_instruction(&mut self, pc: usize, opcode: u8) {
+        if pc >= self.pc_counts.len() {
+            self.pc_counts.resize(pc + 1, 0);
+        }
+        self.pc_counts[pc] += 1;
+        self.opcode_counts[opcode as usize] += 1;
+        self.total += 1;
+    }
+
+    pub(crate) fn record_call(&mut self, target: usize) {
+        match self
+            .call_counts
+            .iter_mut()
+            .find(|(address, _)| *address == target)
+        {
+            Some((_, count)) => *count += 1,
+            None => self.call_counts.push((target, 1)),
+        }
+    }
+
+    /* the label a pc belongs to, `labels` is sorted by offset */
+    fn label_of(labels: &[(String, usize)], pc: usize) -> String {
+        return labels
+            .iter()
+            .rev()
+            .find(|(_, offset)| *offset <= pc)
+            .map_or(NO_LABEL.to_string(), |(name, _)| name.clone());
+    }
+
+    fn add_count(counts: &mut Vec<(String, u64)>, name: String, count: u64) {
+        match counts.iter_mut().find(|(label, _)| *label == name) {
+            Some((_, total)) => *total += count,
+            None => counts.push((name, count)),
+        }
+    }
+
+    /* group the counts by the code labels (name, program offset) */
+    pub fn report(&self, labels: &[(String, usize)]) -> ProfileReport {
+        let mut sorted_labels = labels.to_vec();
+        sorted_labels.sort_by_key(|label| label.1);
+
+        let mut label_counts: Vec<(String, u64)> = Vec::new();
+        for (pc, count) in self.pc_counts.iter().enumerate() {
+            if *count > 0 {
+                let name = Profiler::label_of(&sorted_labels, pc);
+                Profiler::add_count(&mut label_counts, name, *count);
+            }
+        }
+        label_counts.sort_by(|a, b| b.1.cmp(&a.1));
+
+        let mut opcodes: Vec<(OpCode, u64)> = Vec::new();
+        for (opcode, count) in self.opcode_counts.iter().enumerate() {
+            if *count > 0 {
+                opcodes.push((OpCode::from(opcode as u8), *count));
+            }
+        }
+        opcodes.sort_by(|a, b| b.1.cmp(&a.1));
+
+        let mut calls: Vec<(String, u64)> = Vec::new();
+        for (target, count) in &self.call_counts {
+            let name = match sorted_labels.iter().find(|(_, offset)| offset == target) {
+                Some((name, _)) => name.clone(),
+                None => format!("{}", target),
+            };
+            Profiler::add_count(&mut calls, name, *count);
+        }
+        calls.sort_by(|a, b| b.1.cmp(&a.1));
+
+        return ProfileReport {
+            total: self.total,
+            labels: label_counts,
+            opcodes,
+            calls,
+        };
+    }
+}
+
+impl Display for ProfileReport {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let percent = |count: u64| {
+            if self.total == 0 {
+                0.0
+            } else {
+                count as f64 * 100.0 / self.total as f64
+            }
+        };
+        let mut lines = vec![format!("instructions executed: {}", self.total)];
+        lines.push("hot labels:".to_string());
+        for (name, count) in self.labels.iter().take(REPORT_ROWS) {
+            lines.push(format!(
+                "  {:<20} {:>10} {:>6.2}%",
+                name,
+                count,
+                percent(*count)
+            ));
+        }
+        lines.push("instruction mix:".to_string());
+        for (opcode, count) in self.opcodes.iter().take(REPORT_ROWS) {
+            lines.push(format!(
+                "  {:<20} {:>10} {:>6.2}%",
+                format!("{:?}", opcode),
+                count,
+                percent(*count)
+            ));
+        }
+        lines.push("calls:".to_string());
+        for (name, count) in self.calls.iter().take(REPORT_ROWS) {
+            lines.push(format!("  {:<20} {:>10}", name, count));
+        }
+        f.write_str(&lines.join("\n"))
+    }
+}
@@ -0,0 +1,392 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+use crate::assembler::debug_info::DebugInfo;
+use crate::vm::gc::GC_HEADER_SIZE;
+use crate::vm::heap_allocator::{HeapAllocator, HeapBlock};
+use crate::vm::overflow_mode::OverflowMode;
+use crate::vm::overflow_mode::OverflowMode::{Saturating, Trapping, Wrapping};
+use crate::vm::snapshot::SnapshotError::{BadMagic, InvalidValue, Truncated, UnsupportedVersion};
+use crate::vm::vm::VM;
+use crate::vm::vm_state::VmState;
+use crate::vm::vm_state::VmState::{BudgetExhausted, Faulted, Halted, Running};
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+pub const SNAPSHOT_MAGIC: [u8; 4] = [0x44, 0x55, 0x53, 0x53]; // DUSS
+pub const SNAPSHOT_VERSION: u16 = 2;
+
+/*
+ * Layout, all numbers big-endian, lengths are u64 and prefix their data:
+ *
+ *   magic "DUSS", version u16
+ *   registers 32 x i32, float_registers 32 x f64 bits
+ *   pc, instruction_pc, sp, bp, call_depth, stack_size u64
+ *   remainder i32, comparison_flag u8, overflow_mode u8
+ *   state u8 + halt status i32, exit_code u8 + i32
+ *   instruction_budget u8 + u64, executed_instructions u64
+ *   stack [i32], heap [u8], heap blocks [offset u64, size u64, free u8],
+ *   allocations u64, frees u64, gc enabled u8 + threshold u64
+ *   ro_data [u8], program [u8], entry u64, debug section u8 + [u8]
+ *
+ * Host state (syscalls, output/input, tracer, profiler) is not part of it. Labels come
+ * from the debug section, labels given to set_labels are dropped by a restore.
+ */
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SnapshotError {
+    BadMagic,
+    UnsupportedVersion { version: u16 },
+    Truncated { offset: usize },
+    InvalidValue { field: &'static str },
+}
+
+impl Error for SnapshotError {
+    fn description(&self) -> &str {
+        match self {
+            _ => "Snapshot Error:",
+        }
+    }
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            BadMagic => f.write_str("Not a vm snapshot"),
+            UnsupportedVersion { ref version } => {
+                f.write_str(&format!("Unsupported snapshot version {}", version))
+            }
+            Truncated { ref offset } => {
+                f.write_str(&format!("Snapshot truncated at byte {}", offset))
+            }
+            InvalidValue { ref field } => {
+                f.write_str(&format!("Invalid snapshot value for {}", field))
+            }
+        }
+    }
+}
+
+struct SnapshotWriter {
+    bytes: Vec<u8>,
+}
+
+impl SnapshotWriter {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn bytes(&mut self, value: &[u8]) {
+        self.u64(value.len() as u64);
+        self.bytes.extend_from_slice(value);
+    }
+}
+
+struct SnapshotReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> SnapshotReader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], SnapshotError> {
+        if length > self.bytes.len() - self.offset {
+            return Err(Truncated {
+                offset: self.bytes.len(),
+            });
+        }
+        let slice = &self.bytes[self.offset..self.offset + length];
+        self.offset += length;
+        return Ok(slice);
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        return Ok(self.take(1)?[0]);
+    }
+
+    fn bool(&mut self, field: &'static str) -> Result<bool, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(InvalidValue { field }),
+        }
+    }
+
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        let mut value = [0; 2];
+        value.copy_from_slice(self.take(2)?);
+        return Ok(u16::from_be_bytes(value));
+    }
+
+    fn i32(&mut self) -> Result<i32, SnapshotError> {
+        let mut value = [0; 4];
+        value.copy_from_slice(self.take(4)?);
+        return Ok(i32::from_be_bytes(value));
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        let mut value = [0; 8];
+        value.copy_from_slice(self.take(8)?);
+        return Ok(u64::from_be_bytes(value));
+    }
+
+    fn usize(&mut self) -> Result<usize, SnapshotError> {
+        return Ok(self.u64()? as usize);
+    }
+
+    /* a length that must fit in the rest of the snapshot at `item_size` bytes per item */
+    fn length(&mut self, item_size: usize) -> Result<usize, SnapshotError> {
+        let length = self.usize()?;
+        if length > (self.bytes.len() - self.offset) / item_size {
+            return Err(Truncated {
+                offset: self.bytes.len(),
+            });
+        }
+        return Ok(length);
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, SnapshotError> {
+        let length = self.length(1)?;
+        return Ok(self.take(length)?.to_vec());
+    }
+}
+
+fn encode_overflow_mode(mode: OverflowMode) -> u8 {
+    match mode {
+        Wrapping => 0,
+        Saturating => 1,
+        Trapping => 2,
+    }
+}
+
+fn decode_overflow_mode(value: u8) -> Result<OverflowMode, SnapshotError> {
+    match value {
+        0 => Ok(Wrapping),
+        1 => Ok(Saturating),
+        2 => Ok(Trapping),
+        _ => Err(InvalidValue {
+            field: "overflow_mode",
+        }),
+    }
+}
+
+fn encode_state(state: VmState) -> (u8, i32) {
+    match state {
+        Running => (0, 0),
+        Halted(status) => (1, status),
+        Faulted => (2, 0),
+        BudgetExhausted => (3, 0),
+    }
+}
+
+fn decode_state(value: u8, status: i32) -> Result<VmState, SnapshotError> {
+    match value {
+        0 => Ok(Running),
+        1 => Ok(Halted(status)),
+        2 => Ok(Faulted),
+        3 => Ok(BudgetExhausted),
+        _ => Err(InvalidValue { field: "state" }),
+    }
+}
+
+pub(crate) fn encode(vm: &VM) -> Vec<u8> {
+    let mut w = SnapshotWriter { bytes: Vec::new() };
+    w.bytes.extend_from_slice(&SNAPSHOT_MAGIC);
+    w.bytes.extend_from_slice(&SNAPSHOT_VERSION.to_be_bytes());
+
+    for register in vm.registers.iter() {
+        w.i32(*register);
+    }
+    for register in vm.float_registers.iter() {
+        w.u64(register.to_bits());
+    }
+    w.u64(vm.pc as u64);
+    w.u64(vm.instruction_pc as u64);
+    w.u64(vm.sp as u64);
+    w.u64(vm.bp as u64);
+    w.u64(vm.call_depth as u64);
+    w.u64(vm.stack_size as u64);
+    w.i32(vm.remainder);
+    w.u8(vm.comparison_flag as u8);
+    w.u8(encode_overflow_mode(vm.overflow_mode));
+    let (state, status) = encode_state(vm.state);
+    w.u8(state);
+    w.i32(status);
+    w.u8(vm.exit_code.is_some() as u8);
+    w.i32(vm.exit_code.unwrap_or(0));
+    w.u8(vm.instruction_budget.is_some() as u8);
+    w.u64(vm.instruction_budget.unwrap_or(0));
+    w.u64(vm.executed_instructions);
+
+    w.u64(vm.stack.len() as u64);
+    for slot in &vm.stack {
+        w.i32(*slot);
+    }
+    w.bytes(&vm.heap);
+    w.u64(vm.allocator.blocks().len() as u64);
+    for block in vm.allocator.blocks() {
+        w.u64(block.offset as u64);
+        w.u64(block.size as u64);
+        w.u8(block.free as u8);
+    }
+    w.u64(vm.allocator.allocations());
+    w.u64(vm.allocator.frees());
+    w.u8(vm.gc.is_enabled() as u8);
+    w.u64(vm.gc.threshold() as u64);
+
+    w.bytes(&vm.ro_data);
+    w.bytes(&vm.program);
+    w.u64(vm.entry as u64);
+    w.u8(vm.debug_info.is_some() as u8);
+    w.bytes(
+        &vm.debug_info
+            .as_ref()
+            .map_or(Vec::new(), |debug_info| debug_info.encode_to_bytes()),
+    );
+    return w.bytes;
+}
+
+/* nothing in `vm` changes unless the whole snapshot decodes */
+pub(crate) fn decode(vm: &mut VM, bytes: &[u8]) -> Result<(), SnapshotError> {
+    let mut r = SnapshotReader { bytes, offset: 0 };
+    if r.take(4).map_err(|_| BadMagic)? != SNAPSHOT_MAGIC {
+        return Err(BadMagic);
+    }
+    let version = r.u16()?;
+    if version != SNAPSHOT_VERSION {
+        return Err(UnsupportedVersion { version });
+    }
+
+    let mut registers = [0; 32];
+    for register in registers.iter_mut() {
+        *register = r.i32()?;
+    }
+    let mut float_registers = [0.0; 32];
+    for register in float_registers.iter_mut() {
+        *register = f64::from_bits(r.u64()?);
+    }
+    let pc = r.usize()?;
+    let instruction_pc = r.usize()?;
+    let sp = r.usize()?;
+    let bp = r.usize()?;
+    let call_depth = r.usize()?;
+    let stack_size = r.usize()?;
+    let remainder = r.i32()?;
+    let comparison_flag = r.bool("comparison_flag")?;
+    let overflow_mode = decode_overflow_mode(r.u8()?)?;
+    let state = r.u8()?;
+    let status = r.i32()?;
+    let state = decode_state(state, status)?;
+    let has_exit_code = r.bool("exit_code")?;
+    let exit_code = r.i32()?;
+    let has_budget = r.bool("instruction_budget")?;
+    let budget = r.u64()?;
+    let executed_instructions = r.u64()?;
+
+    let stack_length = r.length(4)?;
+    let mut stack = Vec::with_capacity(stack_length);
+    for _ in 0..stack_length {
+        stack.push(r.i32()?);
+    }
+    let heap = r.bytes()?;
+    let block_count = r.length(17)?;
+    let mut blocks = Vec::with_capacity(block_count);
+    let mut blocks_end = 0;
+    for _ in 0..block_count {
+        let offset = r.usize()?;
+        let size = r.usize()?;
+        let free = r.bool("heap block")?;
+        // blocks are sorted, do not overlap and stay inside the heap, as the allocator keeps them
+        if offset < blocks_end
+            || offset
+                .checked_add(size)
+                .map_or(true, |end| end > heap.len())
+        {
+            return Err(InvalidValue {
+                field: "heap block",
+            });
+        }
+        blocks_end = offset + size;
+        blocks.push(HeapBlock { offset, size, free });
+    }
+    let allocations = r.u64()?;
+    let frees = r.u64()?;
+    let gc_enabled = r.bool("gc")?;
+    let gc_threshold = r.usize()?;
+    // the collector reads the header of every allocated block
+    if gc_enabled
+        && blocks
+            .iter()
+            .any(|block| !block.free && block.size < GC_HEADER_SIZE)
+    {
+        return Err(InvalidValue {
+            field: "heap block",
+        });
+    }
+
+    let ro_data = r.bytes()?;
+    let program = r.bytes()?;
+    let entry = r.usize()?;
+    let has_debug_info = r.bool("debug info")?;
+    let debug_bytes = r.bytes()?;
+    let debug_info = if has_debug_info {
+        Some(
+            DebugInfo::decode_from_bytes(&debug_bytes).map_err(|_| InvalidValue {
+                field: "debug info",
+            })?,
+        )
+    } else {
+        None
+    };
+    if r.offset != bytes.len() {
+        return Err(InvalidValue {
+            field: "trailing bytes",
+        });
+    }
+    if sp != stack.len() || bp > stack.len() || stack.len() > stack_size {
+        return Err(InvalidValue { field: "stack" });
+    }
+    if pc > program.len() || instruction_pc > program.len() {
+        return Err(InvalidValue { field: "pc" });
+    }
+    if entry > program.len() {
+        return Err(InvalidValue { field: "entry" });
+    }
+
+    vm.registers = registers;
+    vm.float_registers = float_registers;
+    vm.pc = pc;
+    vm.instruction_pc = instruction_pc;
+    vm.sp = sp;
+    vm.bp = bp;
+    vm.call_depth = call_depth;
+    vm.stack_size = stack_size;
+    vm.remainder = remainder;
+    vm.comparison_flag = comparison_flag;
+    vm.overflow_mode = overflow_mode;
+    vm.state = state;
+    vm.exit_code = if has_exit_code { Some(exit_code) } else { None };
+    vm.instruction_budget = if has_budget { Some(budget) } else { None };
+    vm.executed_instructions = executed_instructions;
+    vm.stack = stack;
+    vm.heap = heap;
+    vm.allocator = HeapAllocator::restore(blocks, allocations, frees);
+    vm.gc.enabled = gc_enabled;
+    vm.gc.set_threshold(gc_threshold);
+    vm.ro_data = ro_data;
+    vm.program = program;
+    vm.entry = entry;
+    vm.labels = debug_info
+        .as_ref()
+        .map_or(Vec::new(), |debug_info| debug_info.code_labels.clone());
+    vm.debug_info = debug_info;
+    return Ok(());
+}
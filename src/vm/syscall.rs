@@ -0,0 +1,152 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+use crate::vm::vm::VM;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/* print $0 as an integer */
+pub const SYS_PRINT_INT: u16 = 0;
+/* print float register 0 */
+pub const SYS_PRINT_FLOAT: u16 = 1;
+/* read a line into heap[$0..$0+$1] without the newline, $0 = bytes written */
+pub const SYS_READ_LINE: u16 = 2;
+/* stop the vm, $0 is the exit code */
+pub const SYS_EXIT: u16 = 3;
+/* $0 = seconds since unix epoch, $1 = milliseconds of the current second */
+pub const SYS_TIME: u16 = 4;
+/* write heap[$0..$0+$1] to the output */
+pub const SYS_WRITE_HEAP: u16 = 5;
+
+/* arguments and results are passed in registers, an Err faults the vm */
+pub type HostFunction = Rc<dyn Fn(&mut VM) -> Result<(), String>>;
+
+#[derive(Clone)]
+pub struct Syscall {
+    pub name: String,
+    pub function: HostFunction,
+}
+
+/* host functions reachable through SYSCALL #number */
+#[derive(Clone)]
+pub struct SyscallTable {
+    syscalls: BTreeMap<u16, Syscall>,
+}
+
+impl SyscallTable {
+    pub fn new() -> SyscallTable {
+        SyscallTable {
+            syscalls: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_defaults() -> SyscallTable {
+        let mut table = SyscallTable::new();
+        table.register(SYS_PRINT_INT, "print_int", Rc::new(sys_print_int));
+        table.register(SYS_PRINT_FLOAT, "print_float", Rc::new(sys_print_float));
+        table.register(SYS_READ_LINE, "read_line", Rc::new(sys_read_line));
+        table.register(SYS_EXIT, "exit", Rc::new(sys_exit));
+        table.register(SYS_TIME, "time", Rc::new(sys_time));
+        table.register(SYS_WRITE_HEAP, "write_heap", Rc::new(sys_write_heap));
+        return table;
+    }
+
+    /* replaces any syscall already registered under `number` */
+    pub fn register(&mut self, number: u16, name: &str, function: HostFunction) {
+        self.syscalls.insert(
+            number,
+            Syscall {
+                name: name.to_string(),
+                function,
+            },
+        );
+    }
+
+    pub fn get(&self, number: u16) -> Option<&Syscall> {
+        return self.syscalls.get(&number);
+    }
+
+    pub fn names(&self) -> Vec<(u16, String)> {
+        return self
+            .syscalls
+            .iter()
+            .map(|(number, syscall)| (*number, syscall.name.clone()))
+            .collect();
+    }
+}
+
+impl Debug for SyscallTable {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&format!("SyscallTable {:?}", self.names()))
+    }
+}
+
+/* heap[address..address+length] or an error message */
+fn heap_range(vm: &VM, address: i32, length: i32) -> Result<(usize, usize), String> {
+    if address < 0 || length < 0 || address as usize + length as usize > vm.heap.len() {
+        return Err(format!(
+            "heap range {}..{} out of bounds",
+            address,
+            address as i64 + length as i64
+        ));
+    }
+    return Ok((address as usize, address as usize + length as usize));
+}
+
+fn write_output(vm: &mut VM, bytes: &[u8]) -> Result<(), String> {
+    match vm.output().write_all(bytes) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn sys_print_int(vm: &mut VM) -> Result<(), String> {
+    let text = vm.registers[0].to_string();
+    return write_output(vm, text.as_bytes());
+}
+
+fn sys_print_float(vm: &mut VM) -> Result<(), String> {
+    let text = vm.float_registers[0].to_string();
+    return write_output(vm, text.as_bytes());
+}
+
+fn sys_read_line(vm: &mut VM) -> Result<(), String> {
+    let (start, end) = heap_range(vm, vm.registers[0], vm.registers[1])?;
+    // a prompt written before the read should be visible
+    let _ = vm.output().flush();
+    let mut line = String::new();
+    if let Err(e) = vm.input().read_line(&mut line) {
+        return Err(e.to_string());
+    }
+    let bytes = line.trim_end_matches(|c| c == '\n' || c == '\r').as_bytes();
+    let count = bytes.len().min(end - start);
+    vm.heap[start..start + count].copy_from_slice(&bytes[..count]);
+    vm.registers[0] = count as i32;
+    Ok(())
+}
+
+fn sys_exit(vm: &mut VM) -> Result<(), String> {
+    vm.exit_code = Some(vm.registers[0]);
+    Ok(())
+}
+
+fn sys_time(vm: &mut VM) -> Result<(), String> {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => {
+            vm.registers[0] = duration.as_secs() as i32;
+            vm.registers[1] = duration.subsec_millis() as i32;
+            Ok(())
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn sys_write_heap(vm: &mut VM) -> Result<(), String> {
+    let (start, end) = heap_range(vm, vm.registers[0], vm.registers[1])?;
+    let bytes = vm.heap[start..end].to_vec();
+    return write_output(vm, &bytes);
+}
@@ -0,0 +1,177 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+use crate::vm::instruction::OpCode;
+use crate::vm::instruction::OperandKind::{Immediate16, Immediate32, Register};
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+use std::io::Write;
+
+pub const DEFAULT_TRACE_LIMIT: usize = 10000;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TraceChange {
+    Register { index: usize, old: i32, new: i32 },
+    FloatRegister { index: usize, old: f64, new: f64 },
+    ComparisonFlag { old: bool, new: bool },
+    Remainder { old: i32, new: i32 },
+}
+
+/* one executed instruction, operands are the raw bytes after the opcode, decoded when printed */
+#[derive(Debug, PartialEq, Clone)]
+pub struct TraceEvent {
+    pub pc: usize,
+    pub opcode: OpCode,
+    pub operands: Vec<u8>,
+    pub changes: Vec<TraceChange>,
+}
+
+pub enum TraceSink {
+    Writer(Box<dyn Write>),
+    Callback(Box<dyn FnMut(&TraceEvent)>),
+}
+
+/* registers and flags an instruction can change, captured before it runs */
+pub(crate) struct TraceSnapshot {
+    pub(crate) registers: [i32; 32],
+    pub(crate) float_registers: [f64; 32],
+    pub(crate) comparison_flag: bool,
+    pub(crate) remainder: i32,
+}
+
+pub struct Tracer {
+    sink: TraceSink,
+    /* events written before tracing stops, None for unlimited */
+    limit: Option<usize>,
+    traced: usize,
+}
+
+impl Tracer {
+    pub fn new(sink: TraceSink, limit: Option<usize>) -> Tracer {
+        Tracer {
+            sink,
+            limit,
+            traced: 0,
+        }
+    }
+
+    pub fn traced(&self) -> usize {
+        return self.traced;
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        return self.limit.map_or(false, |limit| self.traced >= limit);
+    }
+
+    pub(crate) fn changes(before: &TraceSnapshot, after: &TraceSnapshot) -> Vec<TraceChange> {
+        let mut changes = Vec::new();
+        for index in 0..before.registers.len() {
+            if before.registers[index] != after.registers[index] {
+                changes.push(TraceChange::Register {
+                    index,
+                    old: before.registers[index],
+                    new: after.registers[index],
+                });
+            }
+        }
+        for index in 0..before.float_registers.len() {
+            // compare bits so NaN results are still reported once
+            if before.float_registers[index].to_bits() != after.float_registers[index].to_bits() {
+                changes.push(TraceChange::FloatRegister {
+                    index,
+                    old: before.float_registers[index],
+                    new: after.float_registers[index],
+                });
+            }
+        }
+        if before.comparison_flag != after.comparison_flag {
+            changes.push(TraceChange::ComparisonFlag {
+                old: before.comparison_flag,
+                new: after.comparison_flag,
+            });
+        }
+        if before.remainder != after.remainder {
+            changes.push(TraceChange::Remainder {
+                old: before.remainder,
+                new: after.remainder,
+            });
+        }
+        return changes;
+    }
+
+    pub(crate) fn record(&mut self, event: &TraceEvent) {
+        if self.is_exhausted() {
+            return;
+        }
+        self.traced += 1;
+        match self.sink {
+            TraceSink::Writer(ref mut writer) => {
+                let _ = writeln!(writer, "{}", event);
+                if self.limit == Some(self.traced) {
+                    let _ = writeln!(writer, "trace limit of {} reached", self.traced);
+                }
+                let _ = writer.flush();
+            }
+            TraceSink::Callback(ref mut callback) => callback(event),
+        }
+    }
+}
+
+impl Debug for Tracer {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&format!(
+            "Tracer {{ limit: {:?}, traced: {} }}",
+            self.limit, self.traced
+        ))
+    }
+}
+
+impl Display for TraceChange {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            TraceChange::Register { index, old, new } => {
+                f.write_str(&format!("${}: {} -> {}", index, old, new))
+            }
+            TraceChange::FloatRegister { index, old, new } => {
+                f.write_str(&format!("f{}: {} -> {}", index, old, new))
+            }
+            TraceChange::ComparisonFlag { old, new } => {
+                f.write_str(&format!("flag: {} -> {}", old, new))
+            }
+            TraceChange::Remainder { old, new } => {
+                f.write_str(&format!("remainder: {} -> {}", old, new))
+            }
+        }
+    }
+}
+
+impl Display for TraceEvent {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let mut line = format!("{:04} {:?}", self.pc, self.opcode);
+        let mut offset = 0;
+        for kind in self.opcode.operands() {
+            let bytes = match self.operands.get(offset..offset + kind.length()) {
+                Some(bytes) => bytes,
+                None => break,
+            };
+            let value = bytes
+                .iter()
+                .fold(0, |value, byte| (value << 8) | *byte as u32);
+            line.push_str(&match kind {
+                Register => format!(" ${}", value),
+                Immediate16 if self.opcode.has_signed_immediate() => {
+                    format!(" #{}", value as u16 as i16)
+                }
+                Immediate32 if self.opcode.has_signed_immediate() => format!(" #{}", value as i32),
+                _ => format!(" #{}", value),
+            });
+            offset += kind.length();
+        }
+        if !self.changes.is_empty() {
+            let changes: Vec<String> = self.changes.iter().map(|c| c.to_string()).collect();
+            line.push_str(&format!(" | {}", changes.join(", ")));
+        }
+        f.write_str(&line)
+    }
+}
@@ -0,0 +1,147 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+use crate::vm::instruction::OpCode;
+use crate::vm::instruction::OpCode::{CALLI, IGL, JEI, JMPI, JNEI, LOAD, LOAD32, PRTS};
+use crate::vm::instruction::OperandKind::{Immediate16, Immediate32, Register};
+use crate::vm::vm_error::VmError;
+use crate::vm::vm_error::VmError::{
+    BadRoDataOffset, IllegalOpcode, InvalidCallTarget, InvalidRegister, PcOutOfBounds,
+};
+
+/* registers in each of the int and float register files */
+const REGISTER_COUNT: u8 = 32;
+
+struct Decoded {
+    pc: usize,
+    opcode: OpCode,
+    registers: Vec<u8>,
+    immediate: Option<u32>,
+}
+
+/* split the code section into instructions, failing on IGL and on a truncated last one */
+fn decode(program: &[u8]) -> Result<Vec<Decoded>, VmError> {
+    let mut instructions = Vec::new();
+    let mut pc = 0;
+    while pc < program.len() {
+        let opcode = OpCode::from(program[pc]);
+        if opcode == IGL {
+            return Err(IllegalOpcode {
+                opcode: program[pc],
+                pc,
+            });
+        }
+        let end = pc + 1 + opcode.operand_length();
+        if end > program.len() {
+            return Err(PcOutOfBounds {
+                address: program.len() as i64,
+                pc,
+            });
+        }
+
+        let mut registers = Vec::new();
+        let mut immediate = None;
+        let mut offset = pc + 1;
+        for kind in opcode.operands() {
+            let bytes = &program[offset..offset + kind.length()];
+            match kind {
+                Register => registers.push(bytes[0]),
+                Immediate16 => immediate = Some(u16::from_be_bytes([bytes[0], bytes[1]]) as u32),
+                Immediate32 => {
+                    immediate = Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                }
+            }
+            offset += kind.length();
+        }
+        instructions.push(Decoded {
+            pc,
+            opcode,
+            registers,
+            immediate,
+        });
+        pc = end;
+    }
+    return Ok(instructions);
+}
+
+/* true when an instruction starts at `address` */
+fn is_boundary(instructions: &[Decoded], address: usize) -> bool {
+    return instructions
+        .binary_search_by_key(&address, |instruction| instruction.pc)
+        .is_ok();
+}
+
+/* a string constant must start inside ro_data and be NUL terminated, as PRTS reads it */
+fn is_string_constant(ro_data: &[u8], offset: i32) -> bool {
+    return offset >= 0
+        && (offset as usize) < ro_data.len()
+        && ro_data[offset as usize..].contains(&0);
+}
+
+/*
+ * Check a code section before it runs: every opcode decodes, register operands are
+ * below 32, JMPI / JEI / JNEI / CALLI immediates land on an instruction (a jump may
+ * also land on the end of the program, which terminates it), and a PRTS fed by the
+ * LOAD right before it, the sequence the assembler emits for `prts @label`, points
+ * at a string in ro_data. Errors are the faults the instruction would raise at run time.
+ */
+pub fn verify(program: &[u8], ro_data: &[u8]) -> Result<(), VmError> {
+    let instructions = decode(program)?;
+    let is_boundary = |address: u32| is_boundary(&instructions, address as usize);
+
+    let mut loaded: Option<(u8, i32)> = None;
+    for instruction in &instructions {
+        let pc = instruction.pc;
+        for register in &instruction.registers {
+            if *register >= REGISTER_COUNT {
+                return Err(InvalidRegister {
+                    register: *register,
+                    pc,
+                });
+            }
+        }
+
+        match (instruction.opcode, instruction.immediate) {
+            (JMPI, Some(address)) | (JEI, Some(address)) | (JNEI, Some(address)) => {
+                if !is_boundary(address) && address as usize != program.len() {
+                    return Err(PcOutOfBounds {
+                        address: address as i64,
+                        pc,
+                    });
+                }
+            }
+            (CALLI, Some(address)) => {
+                if !is_boundary(address) {
+                    return Err(InvalidCallTarget {
+                        address: address as i64,
+                        pc,
+                    });
+                }
+            }
+            (PRTS, _) => {
+                if let Some((register, offset)) = loaded {
+                    if register == instruction.registers[0] && !is_string_constant(ro_data, offset)
+                    {
+                        return Err(BadRoDataOffset { offset, pc });
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        loaded = match (instruction.opcode, instruction.immediate) {
+            (LOAD, Some(value)) | (LOAD32, Some(value)) => {
+                Some((instruction.registers[0], value as i32))
+            }
+            _ => None,
+        };
+    }
+    return Ok(());
+}
+
+/* the entry must start an instruction, like a CALLI target, or be 0 for an empty program */
+pub fn verify_entry(program: &[u8], entry: usize) -> bool {
+    return (entry == 0 && program.is_empty())
+        || decode(program).map_or(false, |instructions| is_boundary(&instructions, entry));
+}
@@ -1,6 +1,30 @@
-use crate::assembler::elf::ELF_HEADER_PREFIX;
+use crate::assembler::debug_info::DebugInfo;
+use crate::assembler::elf::DELFError::{EntryOutOfBounds, Rejected};
+use crate::assembler::elf::{DELFError, DELFHeader};
+use crate::vm::gc::{GarbageCollector, GcStats};
+use crate::vm::heap_allocator::{HeapAllocator, HeapStats};
 use crate::vm::instruction::OpCode;
+use crate::vm::overflow_mode::OverflowMode;
+use crate::vm::overflow_mode::OverflowMode::{Saturating, Trapping, Wrapping};
+use crate::vm::profiler::{ProfileReport, Profiler};
+use crate::vm::snapshot;
+use crate::vm::snapshot::SnapshotError;
+use crate::vm::syscall::{HostFunction, SyscallTable};
+use crate::vm::trace::{TraceEvent, TraceSink, TraceSnapshot, Tracer};
+use crate::vm::verifier::{verify, verify_entry};
+use crate::vm::vm_error::VmError;
+use crate::vm::vm_error::VmError::{
+    BadRoDataOffset, DivideByZero, HeapOutOfBounds, IllegalOpcode, IntegerOverflow,
+    InvalidAllocationSize, InvalidCallTarget, InvalidFree, InvalidRegister, OutputFailed,
+    PcOutOfBounds, StackOverflow, StackSlotOutOfBounds, StackUnderflow, SyscallFailed,
+    UnknownSyscall,
+};
+use crate::vm::vm_io::VmIo;
+use crate::vm::vm_state::VmState;
+use crate::vm::vm_state::VmState::{BudgetExhausted, Faulted, Halted, Running};
 use std::f64::EPSILON;
+use std::io;
+use std::io::{BufRead, Write};
 use std::str::from_utf8;
 
 pub const DEFAULT_STACK_SIZE: usize = 2097152;
@@ -13,19 +37,53 @@ pub struct VM {
     pub float_registers: [f64; 32],
     /* program counter */
     pub(crate) pc: usize,
+    /* pc of the instruction being executed, reported on faults */
+    pub(crate) instruction_pc: usize,
 
     /* program memory */
     pub program: Vec<u8>,
+    /* pc the loaded program starts at */
+    pub(crate) entry: usize,
+    /* instruction starts in program, rebuilt when program changes length */
+    instruction_boundaries: Vec<bool>,
     pub ro_data: Vec<u8>,
     pub heap: Vec<u8>,
+    pub(crate) allocator: HeapAllocator,
+    pub(crate) gc: GarbageCollector,
 
     pub(crate) sp: usize,
     pub(crate) stack: Vec<i32>,
+    /* maximum number of stack slots */
+    pub(crate) stack_size: usize,
+    /* number of CALLs not yet RET */
+    pub(crate) call_depth: usize,
 
     pub(crate) bp: usize,
 
-    pub(crate) remainder: u32,
+    pub(crate) remainder: i32,
     pub(crate) comparison_flag: bool,
+    pub(crate) overflow_mode: OverflowMode,
+
+    pub(crate) syscalls: SyscallTable,
+    /* set by HLT, HLTR and the exit syscall */
+    pub(crate) exit_code: Option<i32>,
+
+    pub(crate) io: VmIo,
+    /* print "exit(status)" to the output when HLT is executed */
+    pub(crate) exit_banner: bool,
+    pub(crate) tracer: Option<Tracer>,
+    pub(crate) profiler: Option<Profiler>,
+    /* write the profile report to the output when HLT is executed */
+    pub(crate) profile_at_halt: bool,
+    /* code label names and program offsets, used by the profile report */
+    pub(crate) labels: Vec<(String, usize)>,
+    /* debug section of the loaded program, if it has one */
+    pub(crate) debug_info: Option<DebugInfo>,
+
+    pub(crate) state: VmState,
+    /* instructions left before execution stops, None for unlimited */
+    pub(crate) instruction_budget: Option<u64>,
+    pub(crate) executed_instructions: u64,
 }
 
 impl VM {
@@ -34,17 +92,40 @@ impl VM {
             registers: [0; 32],
             float_registers: [0.0; 32],
             pc: 0,
+            instruction_pc: 0,
             program: Vec::new(),
+            entry: 0,
+            instruction_boundaries: Vec::new(),
             ro_data: Vec::new(),
             heap: Vec::new(),
+            allocator: HeapAllocator::new(),
+            gc: GarbageCollector::new(),
 
             sp: 0,
-            stack: Vec::with_capacity(DEFAULT_STACK_SIZE),
+            stack: Vec::new(),
+            stack_size: DEFAULT_STACK_SIZE,
+            call_depth: 0,
 
             bp: 0,
 
             remainder: 0,
             comparison_flag: false,
+            overflow_mode: Wrapping,
+
+            syscalls: SyscallTable::with_defaults(),
+            exit_code: None,
+
+            io: VmIo::stdio(),
+            exit_banner: true,
+            tracer: None,
+            profiler: None,
+            profile_at_halt: false,
+            labels: Vec::new(),
+            debug_info: None,
+
+            state: Running,
+            instruction_budget: None,
+            executed_instructions: 0,
         }
     }
 
@@ -55,430 +136,1620 @@ impl VM {
     }
 
     /* read next 8bits from program */
-    fn next_8_bits(&mut self) -> u8 {
+    fn next_8_bits(&mut self) -> Result<u8, VmError> {
+        if self.pc >= self.program.len() {
+            return Err(PcOutOfBounds {
+                address: self.pc as i64,
+                pc: self.instruction_pc,
+            });
+        }
         let result = self.program[self.pc];
         self.pc += 1;
-        return result;
+        return Ok(result);
     }
 
     /* read next 16bits from program */
-    fn next_16_bits(&mut self) -> u16 {
-        let result = ((self.program[self.pc] as u16) << 8) | self.program[self.pc + 1] as u16;
-        self.pc += 2;
-        return result;
+    fn next_16_bits(&mut self) -> Result<u16, VmError> {
+        let high = self.next_8_bits()? as u16;
+        let low = self.next_8_bits()? as u16;
+        return Ok((high << 8) | low);
+    }
+
+    /* read next 32bits from program */
+    fn next_32_bits(&mut self) -> Result<u32, VmError> {
+        let high = self.next_16_bits()? as u32;
+        let low = self.next_16_bits()? as u32;
+        return Ok((high << 16) | low);
+    }
+
+    /* read next 8bits from program as a register number */
+    fn next_register(&mut self) -> Result<usize, VmError> {
+        let register = self.next_8_bits()?;
+        if register as usize >= self.registers.len() {
+            return Err(InvalidRegister {
+                register,
+                pc: self.instruction_pc,
+            });
+        }
+        return Ok(register as usize);
+    }
+
+    /* check a jump target, jumping past the end of program just terminates it */
+    fn jump_target(&self, target: i64) -> Result<usize, VmError> {
+        if target < 0 {
+            return Err(PcOutOfBounds {
+                address: target,
+                pc: self.instruction_pc,
+            });
+        }
+        return Ok(target as usize);
+    }
+
+    pub fn run_once(&mut self) -> Result<VmState, VmError> {
+        return self.step();
+    }
+
+    pub fn state(&self) -> VmState {
+        return self.state;
+    }
+
+    pub fn executed_instructions(&self) -> u64 {
+        return self.executed_instructions;
+    }
+
+    pub fn instruction_budget(&self) -> Option<u64> {
+        return self.instruction_budget;
+    }
+
+    pub fn set_instruction_budget(&mut self, budget: Option<u64>) {
+        self.instruction_budget = budget;
+    }
+
+    pub fn stack_size(&self) -> usize {
+        return self.stack_size;
+    }
+
+    /* fails when the stack already holds more than `size` slots */
+    pub fn set_stack_size(&mut self, size: usize) -> Result<(), &'static str> {
+        if size < self.stack.len() {
+            return Err("Stack size is smaller than the current stack.");
+        }
+        self.stack_size = size;
+        Ok(())
+    }
+
+    pub fn call_depth(&self) -> usize {
+        return self.call_depth;
+    }
+
+    /* make `function` reachable as SYSCALL #number, replacing any existing one */
+    pub fn register_syscall(&mut self, number: u16, name: &str, function: HostFunction) {
+        self.syscalls.register(number, name, function);
+    }
+
+    pub fn syscalls(&self) -> &SyscallTable {
+        return &self.syscalls;
+    }
+
+    /* program output (PRTS, syscalls, the HLT banner) goes here instead of stdout */
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.io.output = output;
+    }
+
+    /* the read line syscall reads from here instead of stdin */
+    pub fn set_input(&mut self, input: Box<dyn BufRead>) {
+        self.io.input = input;
+    }
+
+    pub fn set_exit_banner(&mut self, enabled: bool) {
+        self.exit_banner = enabled;
+    }
+
+    pub fn output(&mut self) -> &mut dyn Write {
+        return self.io.output.as_mut();
+    }
+
+    pub fn input(&mut self) -> &mut dyn BufRead {
+        return self.io.input.as_mut();
+    }
+
+    fn output_failed(&self, e: io::Error) -> VmError {
+        return OutputFailed {
+            message: e.to_string(),
+            pc: self.instruction_pc,
+        };
+    }
+
+    fn write_output(&mut self, bytes: &[u8]) -> Result<(), VmError> {
+        match self.io.output.write_all(bytes) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(self.output_failed(e)),
+        }
+    }
+
+    fn flush_output(&mut self) -> Result<(), VmError> {
+        match self.io.output.flush() {
+            Ok(_) => Ok(()),
+            Err(e) => Err(self.output_failed(e)),
+        }
+    }
+
+    /* log every executed instruction to `sink`, stops after `limit` instructions */
+    pub fn enable_trace(&mut self, sink: TraceSink, limit: Option<usize>) {
+        self.tracer = Some(Tracer::new(sink, limit));
+    }
+
+    pub fn disable_trace(&mut self) {
+        self.tracer = None;
+    }
+
+    pub fn is_tracing(&self) -> bool {
+        return self
+            .tracer
+            .as_ref()
+            .map_or(false, |tracer| !tracer.is_exhausted());
     }
 
-    pub fn run_once(&mut self) {
-        self.execute_instruction();
+    pub fn tracer(&self) -> Option<&Tracer> {
+        return self.tracer.as_ref();
     }
 
-    fn execute_instruction(&mut self) -> Result<bool, &'static str> {
-        // fly away
+    /* count executed instructions per opcode, pc and CALL target */
+    pub fn enable_profiler(&mut self, report_at_halt: bool) {
+        self.profiler = Some(Profiler::new());
+        self.profile_at_halt = report_at_halt;
+    }
+
+    pub fn disable_profiler(&mut self) {
+        self.profiler = None;
+        self.profile_at_halt = false;
+    }
+
+    pub fn profiler(&self) -> Option<&Profiler> {
+        return self.profiler.as_ref();
+    }
+
+    pub fn profile_report(&self) -> Option<ProfileReport> {
+        return self
+            .profiler
+            .as_ref()
+            .map(|profiler| profiler.report(&self.labels));
+    }
+
+    /* code labels (name, program offset), e.g. Assembler::symbol_table.code_labels() */
+    pub fn set_labels(&mut self, labels: Vec<(String, usize)>) {
+        self.labels = labels;
+    }
+
+    pub fn labels(&self) -> &Vec<(String, usize)> {
+        return &self.labels;
+    }
+
+    pub fn debug_info(&self) -> Option<&DebugInfo> {
+        return self.debug_info.as_ref();
+    }
+
+    /* `for+3 (for_each.asm:6)` with a debug section, else from the labels, e.g. `for+3` */
+    pub fn location(&self, pc: usize) -> String {
+        match self.debug_info {
+            Some(ref debug_info) => debug_info.location(pc),
+            None => {
+                let mut debug_info = DebugInfo::new("");
+                debug_info.code_labels = self.labels.clone();
+                debug_info.location(pc)
+            }
+        }
+    }
+
+    /* machine state in the versioned format described in vm::snapshot */
+    pub fn snapshot(&self) -> Vec<u8> {
+        return snapshot::encode(self);
+    }
+
+    /* resume from a snapshot, the vm is left unchanged when it is rejected */
+    pub fn restore(&mut self, bytes: &[u8]) -> Result<(), SnapshotError> {
+        snapshot::decode(self, bytes)?;
+        self.instruction_boundaries.clear();
+        Ok(())
+    }
+
+    pub fn exit_code(&self) -> Option<i32> {
+        return self.exit_code;
+    }
+
+    pub fn overflow_mode(&self) -> OverflowMode {
+        return self.overflow_mode;
+    }
+
+    pub fn set_overflow_mode(&mut self, mode: OverflowMode) {
+        self.overflow_mode = mode;
+    }
+
+    /* execute one instruction, charging it against the instruction budget */
+    fn step(&mut self) -> Result<VmState, VmError> {
+        // fly away, the end of the program is no instruction and costs no budget
         if self.pc >= self.program.len() {
-            return Ok(true);
+            self.state = Halted(self.exit_code.unwrap_or(0));
+            return Ok(self.state);
+        }
+        if self.instruction_budget == Some(0) {
+            self.state = BudgetExhausted;
+            return Ok(self.state);
+        }
+
+        match self.execute_instruction() {
+            Ok(terminated) => {
+                if let Some(budget) = self.instruction_budget {
+                    self.instruction_budget = Some(budget - 1);
+                }
+                self.executed_instructions += 1;
+                self.state = if terminated || self.pc >= self.program.len() {
+                    Halted(self.exit_code.unwrap_or(0))
+                } else {
+                    Running
+                };
+                return Ok(self.state);
+            }
+            Err(e) => {
+                self.state = Faulted;
+                return Err(e);
+            }
+        }
+    }
+
+    fn execute_instruction(&mut self) -> Result<bool, VmError> {
+        self.instruction_pc = self.pc;
+        if let Some(ref mut profiler) = self.profiler {
+            profiler.record_instruction(self.pc, self.program[self.pc]);
+        }
+        let before = if self.is_tracing() {
+            Some(self.trace_snapshot())
+        } else {
+            None
+        };
+        let result = self.dispatch_instruction();
+        if let Some(before) = before {
+            self.trace_instruction(&before);
+        }
+        if result.is_err() {
+            // leave pc on the faulting instruction so the vm stays inspectable
+            self.pc = self.instruction_pc;
+        }
+        return result;
+    }
+
+    fn trace_snapshot(&self) -> TraceSnapshot {
+        return TraceSnapshot {
+            registers: self.registers,
+            float_registers: self.float_registers,
+            comparison_flag: self.comparison_flag,
+            remainder: self.remainder,
+        };
+    }
+
+    fn trace_instruction(&mut self, before: &TraceSnapshot) {
+        let pc = self.instruction_pc;
+        let opcode = OpCode::from(self.program[pc]);
+        let end = (pc + 1 + opcode.operand_length()).min(self.program.len());
+        let event = TraceEvent {
+            pc,
+            opcode,
+            operands: self.program[pc + 1..end].to_vec(),
+            changes: Tracer::changes(before, &self.trace_snapshot()),
+        };
+        if let Some(ref mut tracer) = self.tracer {
+            tracer.record(&event);
         }
+    }
 
+    fn dispatch_instruction(&mut self) -> Result<bool, VmError> {
         let code = self.decode_opcode();
         match code {
-            OpCode::LOAD => self.handle_load(),
-            OpCode::ADD => self.handle_add(),
-            OpCode::SUB => self.handle_sub(),
-            OpCode::MUL => self.handle_mul(),
-            OpCode::DIV => self.handle_div(),
-            OpCode::JMP => self.handle_jmp(),
-            OpCode::JMPF => self.handle_jmpf(),
-            OpCode::JMPB => self.handle_jmpb(),
-            OpCode::EQ => self.handle_eq(),
-            OpCode::LT => self.handle_lt(),
-            OpCode::LTE => self.handle_lte(),
-            OpCode::GT => self.handle_gt(),
-            OpCode::GTE => self.handle_gte(),
-            OpCode::JE => self.handle_je(),
-            OpCode::JNE => self.handle_jne(),
-            OpCode::JL => self.handle_jl(),
-            OpCode::JG => self.handle_jg(),
-            OpCode::ALOC => self.handle_aloc(),
-            OpCode::INC => self.handle_inc(),
-            OpCode::DEC => self.handle_dec(),
-            OpCode::LOADF64 => self.handle_load_f64(),
-            OpCode::ADDF64 => self.handle_add_f64(),
-            OpCode::SUBF64 => self.handle_sub_f64(),
-            OpCode::MULF64 => self.handle_mul_f64(),
-            OpCode::DIVF64 => self.handle_div_f64(),
-            OpCode::EQF64 => self.handle_eq_f64(),
-            OpCode::NEQF64 => self.handle_neq_f64(),
-            OpCode::GTF64 => self.handle_gt_f64(),
-            OpCode::GTEF64 => self.handle_gte_f64(),
-            OpCode::LTF64 => self.handle_lt_f64(),
-            OpCode::LTEF64 => self.handle_lte_f64(),
-            OpCode::AND => self.handle_and(),
-            OpCode::OR => self.handle_or(),
-            OpCode::XOR => self.handle_xor(),
-            OpCode::NOT => self.handle_not(),
-            OpCode::PUSH => self.handle_push(),
-            OpCode::POP => self.handle_pop(),
-            OpCode::CALL => {
-                /* CALL label_usage */
-                let ret_dest = self.pc + 1;
-
-                let function = self.registers[self.next_8_bits() as usize];
-
-                self.stack.push(ret_dest as i32);
-                self.stack.push(self.bp as i32);
-
-                if self.stack.len() > DEFAULT_STACK_SIZE {
-                    return Err("Error: Stack Overflow.");
+            OpCode::LOAD => self.handle_load()?,
+            OpCode::LOAD32 => self.handle_load32()?,
+            OpCode::ADD => self.handle_add()?,
+            OpCode::SUB => self.handle_sub()?,
+            OpCode::MUL => self.handle_mul()?,
+            OpCode::DIV => self.handle_div()?,
+            OpCode::JMP => self.handle_jmp()?,
+            OpCode::JMPF => self.handle_jmpf()?,
+            OpCode::JMPB => self.handle_jmpb()?,
+            OpCode::EQ => self.handle_eq()?,
+            OpCode::LT => self.handle_lt()?,
+            OpCode::LTE => self.handle_lte()?,
+            OpCode::GT => self.handle_gt()?,
+            OpCode::GTE => self.handle_gte()?,
+            OpCode::JE => self.handle_je()?,
+            OpCode::JNE => self.handle_jne()?,
+            OpCode::JL => self.handle_jl()?,
+            OpCode::JG => self.handle_jg()?,
+            OpCode::ALOC => self.handle_aloc()?,
+            OpCode::FREE => self.handle_free()?,
+            OpCode::INC => self.handle_inc()?,
+            OpCode::DEC => self.handle_dec()?,
+            OpCode::LOADF64 => self.handle_load_f64()?,
+            OpCode::ADDF64 => self.handle_add_f64()?,
+            OpCode::SUBF64 => self.handle_sub_f64()?,
+            OpCode::MULF64 => self.handle_mul_f64()?,
+            OpCode::DIVF64 => self.handle_div_f64()?,
+            OpCode::EQF64 => self.handle_eq_f64()?,
+            OpCode::NEQF64 => self.handle_neq_f64()?,
+            OpCode::GTF64 => self.handle_gt_f64()?,
+            OpCode::GTEF64 => self.handle_gte_f64()?,
+            OpCode::LTF64 => self.handle_lt_f64()?,
+            OpCode::LTEF64 => self.handle_lte_f64()?,
+            OpCode::ITOF => self.handle_itof()?,
+            OpCode::FTOI => self.handle_ftoi()?,
+            OpCode::MOVF64 => self.handle_mov_f64()?,
+            OpCode::NEGF64 => self.handle_neg_f64()?,
+            OpCode::ABSF64 => self.handle_abs_f64()?,
+            OpCode::SQRTF64 => self.handle_sqrt_f64()?,
+            OpCode::CMPF64 => self.handle_cmp_f64()?,
+            OpCode::SHL => self.handle_shl()?,
+            OpCode::SHR => self.handle_shr()?,
+            OpCode::SAR => self.handle_sar()?,
+            OpCode::MOD => self.handle_mod()?,
+            OpCode::DIVU => self.handle_divu()?,
+            OpCode::MODU => self.handle_modu()?,
+            OpCode::LOADREM => self.handle_loadrem()?,
+            OpCode::ADDWRAP => self.handle_add_wrap()?,
+            OpCode::SUBWRAP => self.handle_sub_wrap()?,
+            OpCode::MULWRAP => self.handle_mul_wrap()?,
+            OpCode::ADDCHK => self.handle_add_checked()?,
+            OpCode::SUBCHK => self.handle_sub_checked()?,
+            OpCode::MULCHK => self.handle_mul_checked()?,
+            OpCode::SEQ => self.handle_seq()?,
+            OpCode::SNEQ => self.handle_sneq()?,
+            OpCode::SLT => self.handle_slt()?,
+            OpCode::SLTE => self.handle_slte()?,
+            OpCode::SGT => self.handle_sgt()?,
+            OpCode::SGTE => self.handle_sgte()?,
+            OpCode::SEQF64 => self.handle_seq_f64()?,
+            OpCode::SNEQF64 => self.handle_sneq_f64()?,
+            OpCode::SLTF64 => self.handle_slt_f64()?,
+            OpCode::SLTEF64 => self.handle_slte_f64()?,
+            OpCode::SGTF64 => self.handle_sgt_f64()?,
+            OpCode::SGTEF64 => self.handle_sgte_f64()?,
+            OpCode::ADDI => self.handle_addi()?,
+            OpCode::SUBI => self.handle_subi()?,
+            OpCode::CMPI => self.handle_cmpi()?,
+            OpCode::JMPI => self.handle_jmpi()?,
+            OpCode::JEI => self.handle_jei()?,
+            OpCode::JNEI => self.handle_jnei()?,
+            OpCode::AND => self.handle_and()?,
+            OpCode::OR => self.handle_or()?,
+            OpCode::XOR => self.handle_xor()?,
+            OpCode::NOT => self.handle_not()?,
+            OpCode::PUSH => self.handle_push()?,
+            OpCode::POP => self.handle_pop()?,
+            OpCode::LDB => self.handle_ldb()?,
+            OpCode::LDW => self.handle_ldw()?,
+            OpCode::LDF64 => self.handle_ld_f64()?,
+            OpCode::STB => self.handle_stb()?,
+            OpCode::STW => self.handle_stw()?,
+            OpCode::STF64 => self.handle_st_f64()?,
+            OpCode::CALL => self.handle_call()?,
+            OpCode::CALLI => self.handle_calli()?,
+            OpCode::LDROW => self.handle_ldrow()?,
+            OpCode::SYSCALL => {
+                if self.handle_syscall()? {
+                    self.flush_output()?;
+                    return Ok(true);
                 }
-                self.bp = self.sp;
-
-                self.pc = function as usize;
             }
-            OpCode::RET => self.handle_ret(),
+            OpCode::LOADLOCAL => self.handle_load_local()?,
+            OpCode::STORELOCAL => self.handle_store_local()?,
+            OpCode::RET => self.handle_ret()?,
             OpCode::HLT => {
-                println!("\nexit(0)");
-                return Ok(true);
+                return self.halt(0);
             }
-            OpCode::PRTS => self.handel_prts(),
+            OpCode::HLTR => {
+                let status = self.registers[self.next_register()?];
+                return self.halt(status);
+            }
+            OpCode::PRTS => self.handel_prts()?,
             OpCode::IGL => {
-                print!("Unrecognized opcode {} found! Terminating...", code);
-                return Err("Unrecognized opcode found, Terminated.");
+                return Err(IllegalOpcode {
+                    opcode: self.program[self.instruction_pc],
+                    pc: self.instruction_pc,
+                });
             }
         }
         return Ok(false);
     }
 
-    fn handel_prts(&mut self) -> () {
-        /* PRTS reg */
-        let register = self.next_8_bits() as usize;
-        let start_offset = self.registers[register] as usize;
-        let mut end_offset = start_offset;
+    /* check `size` bytes from base + offset are inside the heap */
+    fn heap_address(&self, base: i32, offset: u16, size: usize) -> Result<usize, VmError> {
+        let address = base as i64 + offset as i64;
+        if address < 0 || address as usize + size > self.heap.len() {
+            return Err(HeapOutOfBounds {
+                address,
+                pc: self.instruction_pc,
+            });
+        }
+        return Ok(address as usize);
+    }
+
+    fn handle_st_f64(&mut self) -> Result<(), VmError> {
+        /* STF64 regSource regBase offsetH offsetL */
+        let value = self.float_registers[self.next_register()?];
+        let base = self.registers[self.next_register()?];
+        let offset = self.next_16_bits()?;
+        let address = self.heap_address(base, offset, 8)?;
+        self.heap[address..address + 8].copy_from_slice(&value.to_be_bytes());
+        Ok(())
+    }
+
+    fn handle_stw(&mut self) -> Result<(), VmError> {
+        /* STW regSource regBase offsetH offsetL */
+        let value = self.registers[self.next_register()?];
+        let base = self.registers[self.next_register()?];
+        let offset = self.next_16_bits()?;
+        let address = self.heap_address(base, offset, 4)?;
+        self.heap[address..address + 4].copy_from_slice(&value.to_be_bytes());
+        Ok(())
+    }
 
+    fn handle_stb(&mut self) -> Result<(), VmError> {
+        /* STB regSource regBase offsetH offsetL */
+        let value = self.registers[self.next_register()?];
+        let base = self.registers[self.next_register()?];
+        let offset = self.next_16_bits()?;
+        let address = self.heap_address(base, offset, 1)?;
+        self.heap[address] = value as u8;
+        Ok(())
+    }
+
+    fn handle_ld_f64(&mut self) -> Result<(), VmError> {
+        /* LDF64 regTarget regBase offsetH offsetL */
+        let target = self.next_register()?;
+        let base = self.registers[self.next_register()?];
+        let offset = self.next_16_bits()?;
+        let address = self.heap_address(base, offset, 8)?;
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&self.heap[address..address + 8]);
+        self.float_registers[target] = f64::from_be_bytes(bytes);
+        Ok(())
+    }
+
+    fn handle_ldw(&mut self) -> Result<(), VmError> {
+        /* LDW regTarget regBase offsetH offsetL */
+        let target = self.next_register()?;
+        let base = self.registers[self.next_register()?];
+        let offset = self.next_16_bits()?;
+        let address = self.heap_address(base, offset, 4)?;
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&self.heap[address..address + 4]);
+        self.registers[target] = i32::from_be_bytes(bytes);
+        Ok(())
+    }
+
+    fn handle_ldrow(&mut self) -> Result<(), VmError> {
+        /* LDROW regTarget regBase offsetH offsetL */
+        let target = self.next_register()?;
+        let base = self.registers[self.next_register()?];
+        let offset = self.next_16_bits()?;
+        let address = base as i64 + offset as i64;
+        if address < 0 || address as usize + 4 > self.ro_data.len() {
+            return Err(BadRoDataOffset {
+                offset: address as i32,
+                pc: self.instruction_pc,
+            });
+        }
+        let address = address as usize;
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&self.ro_data[address..address + 4]);
+        self.registers[target] = i32::from_be_bytes(bytes);
+        Ok(())
+    }
+
+    fn handle_ldb(&mut self) -> Result<(), VmError> {
+        /* LDB regTarget regBase offsetH offsetL */
+        let target = self.next_register()?;
+        let base = self.registers[self.next_register()?];
+        let offset = self.next_16_bits()?;
+        let address = self.heap_address(base, offset, 1)?;
+        self.registers[target] = self.heap[address] as i32;
+        Ok(())
+    }
+
+    fn handel_prts(&mut self) -> Result<(), VmError> {
+        /* PRTS reg */
+        let offset = self.registers[self.next_register()?];
         let slice = self.ro_data.as_slice();
+        if offset < 0 || offset as usize >= slice.len() {
+            return Err(BadRoDataOffset {
+                offset,
+                pc: self.instruction_pc,
+            });
+        }
+
+        let start_offset = offset as usize;
+        let mut end_offset = start_offset;
         while slice[end_offset] != 0 {
             end_offset += 1;
+            if end_offset >= slice.len() {
+                // string constant is not terminated
+                return Err(BadRoDataOffset {
+                    offset,
+                    pc: self.instruction_pc,
+                });
+            }
         }
 
         let result = from_utf8(&slice[start_offset..end_offset]);
         match result {
             Ok(str) => {
-                print!("{}", str);
+                let str = str.to_string();
+                self.write_output(str.as_bytes())?;
+            }
+            Err(e) => {
+                let message = format!(
+                    "Error decoding string constant for PTRS instruction:{:#?}\n",
+                    e
+                );
+                self.write_output(message.as_bytes())?;
             }
-            Err(e) => println!(
-                "Error decoding string constant for PTRS instruction:{:#?}",
-                e
-            ),
         }
+        Ok(())
     }
 
-    fn handle_ret(&mut self) -> () {
+    fn halt(&mut self, status: i32) -> Result<bool, VmError> {
+        self.exit_code = Some(status);
+        if self.exit_banner {
+            let banner = format!("\nexit({})\n", status);
+            self.write_output(banner.as_bytes())?;
+        }
+        if self.profile_at_halt {
+            if let Some(report) = self.profile_report() {
+                let report = format!("{}\n", report);
+                self.write_output(report.as_bytes())?;
+            }
+        }
+        self.flush_output()?;
+        return Ok(true);
+    }
+
+    /* true when the syscall exited, an exit code left by an earlier halt does not count */
+    fn handle_syscall(&mut self) -> Result<bool, VmError> {
+        /* SYSCALL number[15..8] number[7..0] */
+        let number = self.next_16_bits()?;
+        let function = match self.syscalls.get(number) {
+            Some(syscall) => syscall.function.clone(),
+            None => {
+                return Err(UnknownSyscall {
+                    number,
+                    pc: self.instruction_pc,
+                });
+            }
+        };
+        let previous_exit_code = self.exit_code.take();
+        let result = function(self);
+        let exited = self.exit_code.is_some();
+        if !exited {
+            self.exit_code = previous_exit_code;
+        }
+        match result {
+            Ok(_) => Ok(exited),
+            Err(message) => Err(SyscallFailed {
+                number,
+                message,
+                pc: self.instruction_pc,
+            }),
+        }
+    }
+
+    /*
+     * Calling convention, sp is always the stack length and slots are addressed from bp:
+     *
+     *   bp-2-n .. bp-3   arguments, pushed by the caller in order (the last one is at bp-3)
+     *   bp-2             return address, pushed by CALL
+     *   bp-1             caller bp, pushed by CALL
+     *   bp+0 ..          locals, reserved by the callee with PUSH
+     *
+     * The return value is passed in $0. RET drops the locals and restores pc and bp,
+     * the caller then POPs its arguments. Registers are not saved, a caller that needs
+     * one across a CALL keeps it in a local.
+     */
+    fn handle_call(&mut self) -> Result<(), VmError> {
+        /* CALL regTarget */
+        let function = self.registers[self.next_register()?];
+        return self.call(function as i64);
+    }
+
+    fn handle_calli(&mut self) -> Result<(), VmError> {
+        /* CALLI address[31..24] address[23..16] address[15..8] address[7..0] */
+        let function = self.next_32_bits()?;
+        return self.call(function as i64);
+    }
+
+    fn is_instruction_boundary(&mut self, address: usize) -> bool {
+        if self.instruction_boundaries.len() != self.program.len() {
+            let mut boundaries = vec![false; self.program.len()];
+            let mut pc = 0;
+            while pc < self.program.len() {
+                boundaries[pc] = true;
+                pc += 1 + OpCode::from(self.program[pc]).operand_length();
+            }
+            self.instruction_boundaries = boundaries;
+        }
+        return address < self.program.len() && self.instruction_boundaries[address];
+    }
+
+    fn call(&mut self, function: i64) -> Result<(), VmError> {
+        if function < 0 || !self.is_instruction_boundary(function as usize) {
+            return Err(InvalidCallTarget {
+                address: function,
+                pc: self.instruction_pc,
+            });
+        }
+        let ret_dest = self.pc;
+
+        self.check_stack_space(2)?;
+        self.stack.push(ret_dest as i32);
+        self.stack.push(self.bp as i32);
+        self.bp = self.stack.len();
+        self.sp = self.stack.len();
+        self.call_depth += 1;
+        if let Some(ref mut profiler) = self.profiler {
+            profiler.record_call(function as usize);
+        }
+
+        self.pc = function as usize;
+        Ok(())
+    }
+
+    fn handle_ret(&mut self) -> Result<(), VmError> {
         /* RET */
-        self.sp = self.bp;
+        if self.call_depth == 0 || self.bp < 2 || self.bp > self.stack.len() {
+            return Err(self.stack_underflow());
+        }
+        self.stack.truncate(self.bp);
         self.bp = self.stack.pop().unwrap() as usize;
         self.pc = self.stack.pop().unwrap() as usize;
+        self.sp = self.stack.len();
+        self.call_depth -= 1;
+        Ok(())
+    }
+
+    fn check_stack_space(&self, slots: usize) -> Result<(), VmError> {
+        if self.stack.len() + slots > self.stack_size {
+            return Err(StackOverflow {
+                depth: self.call_depth,
+                pc: self.instruction_pc,
+            });
+        }
+        Ok(())
+    }
+
+    fn stack_underflow(&self) -> VmError {
+        return StackUnderflow {
+            depth: self.call_depth,
+            pc: self.instruction_pc,
+        };
     }
 
-    fn handle_pop(&mut self) -> () {
+    /* index into stack of the slot `offset` away from bp */
+    fn stack_slot(&self, offset: i32) -> Result<usize, VmError> {
+        let slot = self.bp as i64 + offset as i64;
+        if slot < 0 || slot >= self.stack.len() as i64 {
+            return Err(StackSlotOutOfBounds {
+                offset,
+                pc: self.instruction_pc,
+            });
+        }
+        return Ok(slot as usize);
+    }
+
+    fn handle_load_local(&mut self) -> Result<(), VmError> {
+        /* LOADLOCAL regTarget #offset */
+        let register = self.next_register()?;
+        let offset = self.next_immediate()?;
+        let slot = self.stack_slot(offset)?;
+        self.registers[register] = self.stack[slot];
+        Ok(())
+    }
+
+    fn handle_store_local(&mut self) -> Result<(), VmError> {
+        /* STORELOCAL reg #offset */
+        let value = self.registers[self.next_register()?];
+        let offset = self.next_immediate()?;
+        let slot = self.stack_slot(offset)?;
+        self.stack[slot] = value;
+        Ok(())
+    }
+
+    fn handle_pop(&mut self) -> Result<(), VmError> {
         /* POP reg1 */
-        let register1 = self.next_8_bits() as usize;
+        let register1 = self.next_register()?;
+        // the saved return address and bp of the current frame are not POPable
+        let frame_base = if self.call_depth > 0 { self.bp } else { 0 };
+        if self.stack.len() <= frame_base {
+            return Err(self.stack_underflow());
+        }
         self.registers[register1] = self.stack.pop().unwrap();
-        self.sp -= 1;
+        self.sp = self.stack.len();
+        Ok(())
     }
 
-    fn handle_push(&mut self) -> () {
+    fn handle_push(&mut self) -> Result<(), VmError> {
         /* PUSH reg1 */
-        let register1 = self.registers[self.next_8_bits() as usize];
+        let register1 = self.registers[self.next_register()?];
+        self.check_stack_space(1)?;
         self.stack.push(register1);
-        self.sp += 1;
+        self.sp = self.stack.len();
+        Ok(())
+    }
+
+    fn handle_mul_checked(&mut self) -> Result<(), VmError> {
+        /* MULCHK reg1 reg2 regTarget */
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        let result = self.checked(register1.checked_mul(register2))?;
+        self.registers[self.next_register()?] = result;
+        Ok(())
+    }
+
+    fn handle_sub_checked(&mut self) -> Result<(), VmError> {
+        /* SUBCHK reg1 reg2 regTarget */
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        let result = self.checked(register1.checked_sub(register2))?;
+        self.registers[self.next_register()?] = result;
+        Ok(())
+    }
+
+    fn handle_add_checked(&mut self) -> Result<(), VmError> {
+        /* ADDCHK reg1 reg2 regTarget */
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        let result = self.checked(register1.checked_add(register2))?;
+        self.registers[self.next_register()?] = result;
+        Ok(())
+    }
+
+    /* pick the result matching the VM overflow mode */
+    fn overflow(&self, checked: Option<i32>, wrapped: i32, saturated: i32) -> Result<i32, VmError> {
+        match self.overflow_mode {
+            Wrapping => {
+                return Ok(wrapped);
+            }
+            Saturating => {
+                return Ok(saturated);
+            }
+            Trapping => {
+                return self.checked(checked);
+            }
+        }
+    }
+
+    /* turn a checked arithmetic result into an IntegerOverflow fault */
+    fn checked(&self, result: Option<i32>) -> Result<i32, VmError> {
+        match result {
+            Some(value) => {
+                return Ok(value);
+            }
+            None => {
+                return Err(IntegerOverflow {
+                    pc: self.instruction_pc,
+                });
+            }
+        }
+    }
+
+    fn handle_mul_wrap(&mut self) -> Result<(), VmError> {
+        /* MULWRAP reg1 reg2 regTarget */
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        self.registers[self.next_register()?] = register1.wrapping_mul(register2);
+        Ok(())
     }
 
-    fn handle_not(&mut self) -> () {
+    fn handle_sub_wrap(&mut self) -> Result<(), VmError> {
+        /* SUBWRAP reg1 reg2 regTarget */
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        self.registers[self.next_register()?] = register1.wrapping_sub(register2);
+        Ok(())
+    }
+
+    fn handle_add_wrap(&mut self) -> Result<(), VmError> {
+        /* ADDWRAP reg1 reg2 regTarget */
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        self.registers[self.next_register()?] = register1.wrapping_add(register2);
+        Ok(())
+    }
+
+    fn handle_loadrem(&mut self) -> Result<(), VmError> {
+        /* LOADREM regTarget, remainder of the last DIV */
+        self.registers[self.next_register()?] = self.remainder;
+        Ok(())
+    }
+
+    fn handle_modu(&mut self) -> Result<(), VmError> {
+        /* MODU reg1 reg2 regTarget, registers as u32 */
+        let register1 = self.registers[self.next_register()?] as u32;
+        let register2 = self.registers[self.next_register()?] as u32;
+        if register2 == 0 {
+            return Err(DivideByZero {
+                pc: self.instruction_pc,
+            });
+        }
+        self.registers[self.next_register()?] = (register1 % register2) as i32;
+        Ok(())
+    }
+
+    fn handle_divu(&mut self) -> Result<(), VmError> {
+        /* DIVU reg1 reg2 regTarget, registers as u32 */
+        let register1 = self.registers[self.next_register()?] as u32;
+        let register2 = self.registers[self.next_register()?] as u32;
+        if register2 == 0 {
+            return Err(DivideByZero {
+                pc: self.instruction_pc,
+            });
+        }
+        self.registers[self.next_register()?] = (register1 / register2) as i32;
+        self.remainder = (register1 % register2) as i32;
+        Ok(())
+    }
+
+    fn handle_mod(&mut self) -> Result<(), VmError> {
+        /* MOD reg1 reg2 regTarget, result takes the sign of reg1 */
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        if register2 == 0 {
+            return Err(DivideByZero {
+                pc: self.instruction_pc,
+            });
+        }
+        self.registers[self.next_register()?] = register1.wrapping_rem(register2);
+        Ok(())
+    }
+
+    fn handle_sar(&mut self) -> Result<(), VmError> {
+        /* SAR reg1 reg2 regTarget, arithmetic shift, shift amount is taken mod 32 */
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        self.registers[self.next_register()?] = register1.wrapping_shr(register2 as u32);
+        Ok(())
+    }
+
+    fn handle_shr(&mut self) -> Result<(), VmError> {
+        /* SHR reg1 reg2 regTarget, logical shift, shift amount is taken mod 32 */
+        let register1 = self.registers[self.next_register()?] as u32;
+        let register2 = self.registers[self.next_register()?];
+        self.registers[self.next_register()?] = register1.wrapping_shr(register2 as u32) as i32;
+        Ok(())
+    }
+
+    fn handle_shl(&mut self) -> Result<(), VmError> {
+        /* SHL reg1 reg2 regTarget, shift amount is taken mod 32 */
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        self.registers[self.next_register()?] = register1.wrapping_shl(register2 as u32);
+        Ok(())
+    }
+
+    fn handle_not(&mut self) -> Result<(), VmError> {
         /* NOT reg1 regTarget */
-        let register1 = self.registers[self.next_8_bits() as usize];
-        self.registers[self.next_8_bits() as usize] = !register1;
+        let register1 = self.registers[self.next_register()?];
+        self.registers[self.next_register()?] = !register1;
+        Ok(())
     }
 
-    fn handle_xor(&mut self) -> () {
+    fn handle_xor(&mut self) -> Result<(), VmError> {
         /* XOR reg1 reg2 regTarget */
-        let register1 = self.registers[self.next_8_bits() as usize];
-        let register2 = self.registers[self.next_8_bits() as usize];
-        self.registers[self.next_8_bits() as usize] = register1 ^ register2;
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        self.registers[self.next_register()?] = register1 ^ register2;
+        Ok(())
     }
 
-    fn handle_or(&mut self) -> () {
+    fn handle_or(&mut self) -> Result<(), VmError> {
         /* OR reg1 reg2 regTarget */
-        let register1 = self.registers[self.next_8_bits() as usize];
-        let register2 = self.registers[self.next_8_bits() as usize];
-        self.registers[self.next_8_bits() as usize] = register1 | register2;
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        self.registers[self.next_register()?] = register1 | register2;
+        Ok(())
     }
 
-    fn handle_and(&mut self) -> () {
+    fn handle_and(&mut self) -> Result<(), VmError> {
         /* AND reg1 reg2 regTarget */
-        let register1 = self.registers[self.next_8_bits() as usize];
-        let register2 = self.registers[self.next_8_bits() as usize];
-        self.registers[self.next_8_bits() as usize] = register1 & register2;
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        self.registers[self.next_register()?] = register1 & register2;
+        Ok(())
+    }
+
+    /* read next 16bits from program as a signed immediate */
+    fn next_immediate(&mut self) -> Result<i32, VmError> {
+        return Ok(self.next_16_bits()? as i16 as i32);
+    }
+
+    fn handle_addi(&mut self) -> Result<(), VmError> {
+        /* ADDI reg #immediate regTarget */
+        let register = self.registers[self.next_register()?];
+        let immediate = self.next_immediate()?;
+        let result = self.overflow(
+            register.checked_add(immediate),
+            register.wrapping_add(immediate),
+            register.saturating_add(immediate),
+        )?;
+        self.registers[self.next_register()?] = result;
+        Ok(())
+    }
+
+    fn handle_subi(&mut self) -> Result<(), VmError> {
+        /* SUBI reg #immediate regTarget */
+        let register = self.registers[self.next_register()?];
+        let immediate = self.next_immediate()?;
+        let result = self.overflow(
+            register.checked_sub(immediate),
+            register.wrapping_sub(immediate),
+            register.saturating_sub(immediate),
+        )?;
+        self.registers[self.next_register()?] = result;
+        Ok(())
+    }
+
+    fn handle_cmpi(&mut self) -> Result<(), VmError> {
+        /* CMPI reg #immediate */
+        let register = self.registers[self.next_register()?];
+        let immediate = self.next_immediate()?;
+        self.comparison_flag = register == immediate;
+        Ok(())
+    }
+
+    fn handle_jmpi(&mut self) -> Result<(), VmError> {
+        /* JMPI address[31..24] address[23..16] address[15..8] address[7..0] */
+        let target = self.next_32_bits()?;
+        self.pc = self.jump_target(target as i64)?;
+        Ok(())
+    }
+
+    fn handle_jei(&mut self) -> Result<(), VmError> {
+        /* JEI address[31..24] address[23..16] address[15..8] address[7..0] */
+        let target = self.next_32_bits()?;
+        if self.comparison_flag {
+            self.pc = self.jump_target(target as i64)?;
+        }
+        Ok(())
+    }
+
+    fn handle_jnei(&mut self) -> Result<(), VmError> {
+        /* JNEI address[31..24] address[23..16] address[15..8] address[7..0] */
+        let target = self.next_32_bits()?;
+        if !self.comparison_flag {
+            self.pc = self.jump_target(target as i64)?;
+        }
+        Ok(())
+    }
+
+    fn handle_seq(&mut self) -> Result<(), VmError> {
+        /* SEQ reg1 reg2 regTarget */
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        self.registers[self.next_register()?] = (register1 == register2) as i32;
+        Ok(())
+    }
+
+    fn handle_sneq(&mut self) -> Result<(), VmError> {
+        /* SNEQ reg1 reg2 regTarget */
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        self.registers[self.next_register()?] = (register1 != register2) as i32;
+        Ok(())
+    }
+
+    fn handle_slt(&mut self) -> Result<(), VmError> {
+        /* SLT reg1 reg2 regTarget */
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        self.registers[self.next_register()?] = (register1 < register2) as i32;
+        Ok(())
+    }
+
+    fn handle_slte(&mut self) -> Result<(), VmError> {
+        /* SLTE reg1 reg2 regTarget */
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        self.registers[self.next_register()?] = (register1 <= register2) as i32;
+        Ok(())
+    }
+
+    fn handle_sgt(&mut self) -> Result<(), VmError> {
+        /* SGT reg1 reg2 regTarget */
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        self.registers[self.next_register()?] = (register1 > register2) as i32;
+        Ok(())
+    }
+
+    fn handle_sgte(&mut self) -> Result<(), VmError> {
+        /* SGTE reg1 reg2 regTarget */
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        self.registers[self.next_register()?] = (register1 >= register2) as i32;
+        Ok(())
+    }
+
+    fn handle_seq_f64(&mut self) -> Result<(), VmError> {
+        /* SEQF64 reg1 reg2 regTarget, reg1 and reg2 are float registers */
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
+        self.registers[self.next_register()?] = ((register1 - register2).abs() < EPSILON) as i32;
+        Ok(())
+    }
+
+    fn handle_sneq_f64(&mut self) -> Result<(), VmError> {
+        /* SNEQF64 reg1 reg2 regTarget, reg1 and reg2 are float registers */
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
+        self.registers[self.next_register()?] = ((register1 - register2).abs() >= EPSILON) as i32;
+        Ok(())
+    }
+
+    fn handle_slt_f64(&mut self) -> Result<(), VmError> {
+        /* SLTF64 reg1 reg2 regTarget, reg1 and reg2 are float registers */
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
+        self.registers[self.next_register()?] = (register1 < register2) as i32;
+        Ok(())
+    }
+
+    fn handle_slte_f64(&mut self) -> Result<(), VmError> {
+        /* SLTEF64 reg1 reg2 regTarget, reg1 and reg2 are float registers */
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
+        self.registers[self.next_register()?] = (register1 <= register2) as i32;
+        Ok(())
+    }
+
+    fn handle_sgt_f64(&mut self) -> Result<(), VmError> {
+        /* SGTF64 reg1 reg2 regTarget, reg1 and reg2 are float registers */
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
+        self.registers[self.next_register()?] = (register1 > register2) as i32;
+        Ok(())
+    }
+
+    fn handle_sgte_f64(&mut self) -> Result<(), VmError> {
+        /* SGTEF64 reg1 reg2 regTarget, reg1 and reg2 are float registers */
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
+        self.registers[self.next_register()?] = (register1 >= register2) as i32;
+        Ok(())
+    }
+
+    fn handle_cmp_f64(&mut self) -> Result<(), VmError> {
+        /* CMPF64 reg1 reg2 regTarget, regTarget is an int register set to -1 / 0 / 1 */
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
+        self.registers[self.next_register()?] = if (register1 - register2).abs() < EPSILON {
+            0
+        } else if register1 < register2 {
+            -1
+        } else {
+            1
+        };
+        Ok(())
     }
 
-    fn handle_lte_f64(&mut self) -> () {
+    fn handle_sqrt_f64(&mut self) -> Result<(), VmError> {
+        /* SQRTF64 reg1 regTarget */
+        let register1 = self.float_registers[self.next_register()?];
+        self.float_registers[self.next_register()?] = register1.sqrt();
+        Ok(())
+    }
+
+    fn handle_abs_f64(&mut self) -> Result<(), VmError> {
+        /* ABSF64 reg1 regTarget */
+        let register1 = self.float_registers[self.next_register()?];
+        self.float_registers[self.next_register()?] = register1.abs();
+        Ok(())
+    }
+
+    fn handle_neg_f64(&mut self) -> Result<(), VmError> {
+        /* NEGF64 reg1 regTarget */
+        let register1 = self.float_registers[self.next_register()?];
+        self.float_registers[self.next_register()?] = -register1;
+        Ok(())
+    }
+
+    fn handle_mov_f64(&mut self) -> Result<(), VmError> {
+        /* MOVF64 reg1 regTarget */
+        let register1 = self.float_registers[self.next_register()?];
+        self.float_registers[self.next_register()?] = register1;
+        Ok(())
+    }
+
+    fn handle_ftoi(&mut self) -> Result<(), VmError> {
+        /* FTOI floatReg intRegTarget, truncates toward zero and saturates */
+        let register1 = self.float_registers[self.next_register()?];
+        self.registers[self.next_register()?] = register1 as i32;
+        Ok(())
+    }
+
+    fn handle_itof(&mut self) -> Result<(), VmError> {
+        /* ITOF intReg floatRegTarget */
+        let register1 = self.registers[self.next_register()?];
+        self.float_registers[self.next_register()?] = f64::from(register1);
+        Ok(())
+    }
+
+    fn handle_lte_f64(&mut self) -> Result<(), VmError> {
         /* LTEF64 reg1 reg2 regTarget */
-        let register1 = self.float_registers[self.next_8_bits() as usize];
-        let register2 = self.float_registers[self.next_8_bits() as usize];
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
         self.comparison_flag = register1 <= register2;
+        Ok(())
     }
 
-    fn handle_lt_f64(&mut self) -> () {
+    fn handle_lt_f64(&mut self) -> Result<(), VmError> {
         /* LTF64 reg1 reg2 regTarget */
-        let register1 = self.float_registers[self.next_8_bits() as usize];
-        let register2 = self.float_registers[self.next_8_bits() as usize];
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
         self.comparison_flag = register1 < register2;
+        Ok(())
     }
 
-    fn handle_gte_f64(&mut self) -> () {
+    fn handle_gte_f64(&mut self) -> Result<(), VmError> {
         /* GTEF64 reg1 reg2 regTarget */
-        let register1 = self.float_registers[self.next_8_bits() as usize];
-        let register2 = self.float_registers[self.next_8_bits() as usize];
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
         self.comparison_flag = register1 >= register2;
+        Ok(())
     }
 
-    fn handle_gt_f64(&mut self) -> () {
+    fn handle_gt_f64(&mut self) -> Result<(), VmError> {
         /* GTF64 reg1 reg2 regTarget */
-        let register1 = self.float_registers[self.next_8_bits() as usize];
-        let register2 = self.float_registers[self.next_8_bits() as usize];
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
         self.comparison_flag = register1 > register2;
+        Ok(())
     }
 
-    fn handle_neq_f64(&mut self) -> () {
+    fn handle_neq_f64(&mut self) -> Result<(), VmError> {
         /* NEQF64 reg1 reg2 regTarget */
-        let register1 = self.float_registers[self.next_8_bits() as usize];
-        let register2 = self.float_registers[self.next_8_bits() as usize];
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
         self.comparison_flag = (register1 - register2).abs() > EPSILON;
+        Ok(())
     }
 
-    fn handle_eq_f64(&mut self) -> () {
+    fn handle_eq_f64(&mut self) -> Result<(), VmError> {
         /* EQF64 reg1 reg2 regTarget */
-        let register1 = self.float_registers[self.next_8_bits() as usize];
-        let register2 = self.float_registers[self.next_8_bits() as usize];
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
         self.comparison_flag = (register1 - register2).abs() < EPSILON;
+        Ok(())
     }
 
-    fn handle_div_f64(&mut self) -> () {
+    fn handle_div_f64(&mut self) -> Result<(), VmError> {
         /* DIVF64 reg1 reg2 regTarget */
-        let register1 = self.float_registers[self.next_8_bits() as usize];
-        let register2 = self.float_registers[self.next_8_bits() as usize];
-        self.float_registers[self.next_8_bits() as usize] = register1 / register2;
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
+        self.float_registers[self.next_register()?] = register1 / register2;
+        Ok(())
     }
 
-    fn handle_mul_f64(&mut self) -> () {
+    fn handle_mul_f64(&mut self) -> Result<(), VmError> {
         /* ADDF64 reg1 reg2 regTarget */
-        let register1 = self.float_registers[self.next_8_bits() as usize];
-        let register2 = self.float_registers[self.next_8_bits() as usize];
-        self.float_registers[self.next_8_bits() as usize] = register1 * register2;
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
+        self.float_registers[self.next_register()?] = register1 * register2;
+        Ok(())
     }
 
-    fn handle_sub_f64(&mut self) -> () {
+    fn handle_sub_f64(&mut self) -> Result<(), VmError> {
         /* ADDF64 reg1 reg2 regTarget */
-        let register1 = self.float_registers[self.next_8_bits() as usize];
-        let register2 = self.float_registers[self.next_8_bits() as usize];
-        self.float_registers[self.next_8_bits() as usize] = register1 - register2;
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
+        self.float_registers[self.next_register()?] = register1 - register2;
+        Ok(())
     }
 
-    fn handle_add_f64(&mut self) -> () {
+    fn handle_add_f64(&mut self) -> Result<(), VmError> {
         /* ADDF64 reg1 reg2 regTarget */
-        let register1 = self.float_registers[self.next_8_bits() as usize];
-        let register2 = self.float_registers[self.next_8_bits() as usize];
-        self.float_registers[self.next_8_bits() as usize] = register1 + register2;
+        let register1 = self.float_registers[self.next_register()?];
+        let register2 = self.float_registers[self.next_register()?];
+        self.float_registers[self.next_register()?] = register1 + register2;
+        Ok(())
     }
 
-    fn handle_load_f64(&mut self) -> () {
+    fn handle_load_f64(&mut self) -> Result<(), VmError> {
         /* LOADF64 reg numberH numberL*/
-        let register = self.next_8_bits() as usize;
-        let number = f64::from(self.next_16_bits());
+        let register = self.next_register()?;
+        let number = f64::from(self.next_16_bits()?);
         self.float_registers[register] = number;
+        Ok(())
     }
 
-    fn handle_dec(&mut self) -> () {
+    fn handle_dec(&mut self) -> Result<(), VmError> {
         /* DEC reg */
-        self.registers[self.next_8_bits() as usize] -= 1;
+        let register = self.next_register()?;
+        let value = self.registers[register];
+        self.registers[register] = self.overflow(
+            value.checked_sub(1),
+            value.wrapping_sub(1),
+            value.saturating_sub(1),
+        )?;
+        Ok(())
     }
 
-    fn handle_inc(&mut self) -> () {
+    fn handle_inc(&mut self) -> Result<(), VmError> {
         /* INC reg */
-        self.registers[self.next_8_bits() as usize] += 1;
+        let register = self.next_register()?;
+        let value = self.registers[register];
+        self.registers[register] = self.overflow(
+            value.checked_add(1),
+            value.wrapping_add(1),
+            value.saturating_add(1),
+        )?;
+        Ok(())
+    }
+
+    fn handle_aloc(&mut self) -> Result<(), VmError> {
+        /* ALOC regSize regTarget */
+        let size = self.registers[self.next_register()?];
+        let target = self.next_register()?;
+        if size < 0 {
+            return Err(InvalidAllocationSize {
+                size,
+                pc: self.instruction_pc,
+            });
+        }
+        let address = if self.gc.is_enabled() {
+            let used_bytes = self.allocator.stats(self.heap.len()).used_bytes;
+            if self.gc.should_collect(used_bytes + size as usize) {
+                self.collect_garbage();
+            }
+            self.gc
+                .allocate(&mut self.heap, &mut self.allocator, size as usize)
+        } else {
+            self.allocator.allocate(&mut self.heap, size as usize)
+        };
+        self.registers[target] = address as i32;
+        Ok(())
+    }
+
+    fn handle_free(&mut self) -> Result<(), VmError> {
+        /* FREE regAddress */
+        let address = self.registers[self.next_register()?];
+        let freed = if address < 0 {
+            false
+        } else if self.gc.is_enabled() {
+            self.gc.free(&mut self.allocator, address as usize)
+        } else {
+            self.allocator.free(address as usize)
+        };
+        if !freed {
+            return Err(InvalidFree {
+                address,
+                pc: self.instruction_pc,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn remainder(&self) -> i32 {
+        return self.remainder;
+    }
+
+    pub fn heap_stats(&self) -> HeapStats {
+        return self.allocator.stats(self.heap.len());
+    }
+
+    /* switch ALOC to gc blocks, only allowed before anything is allocated */
+    pub fn enable_gc(&mut self, threshold: usize) -> Result<(), &'static str> {
+        if !self.allocator.blocks().is_empty() {
+            return Err("GC mode must be enabled before the first allocation.");
+        }
+        self.gc.enable(threshold);
+        Ok(())
     }
 
-    fn handle_aloc(&mut self) -> () {
-        let register = self.next_8_bits() as usize;
-        let bytes = self.registers[register];
-        let new_end = self.heap.len() as i32 + bytes;
-        self.heap.resize(new_end as usize, 0);
+    pub fn gc_stats(&self) -> GcStats {
+        return self.gc.stats();
     }
 
-    fn handle_jg(&mut self) -> () {
+    /* registers and the whole stack, including CALL frames, are the roots */
+    pub fn collect_garbage(&mut self) -> GcStats {
+        let mut roots: Vec<i32> = self.registers.to_vec();
+        roots.extend_from_slice(&self.stack);
+        return self.gc.collect(&mut self.heap, &mut self.allocator, &roots);
+    }
+
+    fn handle_jg(&mut self) -> Result<(), VmError> {
         /* JG regTarget */
-        let target = self.registers[self.next_8_bits() as usize];
+        let target = self.registers[self.next_register()?];
         if self.comparison_flag {
-            self.pc = target as usize;
+            self.pc = self.jump_target(target as i64)?;
         }
+        Ok(())
     }
 
-    fn handle_jl(&mut self) -> () {
+    fn handle_jl(&mut self) -> Result<(), VmError> {
         /* JL regTarget */
-        let target = self.registers[self.next_8_bits() as usize];
+        let target = self.registers[self.next_register()?];
         if self.comparison_flag {
-            self.pc = target as usize;
+            self.pc = self.jump_target(target as i64)?;
         }
+        Ok(())
     }
 
-    fn handle_jne(&mut self) -> () {
+    fn handle_jne(&mut self) -> Result<(), VmError> {
         /* JNE regTarget */
-        let target = self.registers[self.next_8_bits() as usize];
+        let target = self.registers[self.next_register()?];
         if !self.comparison_flag {
-            self.pc = target as usize;
+            self.pc = self.jump_target(target as i64)?;
         }
+        Ok(())
     }
 
-    fn handle_je(&mut self) -> () {
+    fn handle_je(&mut self) -> Result<(), VmError> {
         /* JE regTarget */
-        let target = self.registers[self.next_8_bits() as usize];
+        let target = self.registers[self.next_register()?];
         if self.comparison_flag {
-            self.pc = target as usize;
+            self.pc = self.jump_target(target as i64)?;
         }
+        Ok(())
     }
 
-    fn handle_gte(&mut self) -> () {
+    fn handle_gte(&mut self) -> Result<(), VmError> {
         /* GTE reg0 reg1 */
-        let register1 = self.registers[self.next_8_bits() as usize];
-        let register2 = self.registers[self.next_8_bits() as usize];
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
 
         if register1 >= register2 {
             self.comparison_flag = true;
         } else {
             self.comparison_flag = false;
         }
+        Ok(())
     }
 
-    fn handle_gt(&mut self) -> () {
+    fn handle_gt(&mut self) -> Result<(), VmError> {
         /* LT reg0 reg1 */
-        let register1 = self.registers[self.next_8_bits() as usize];
-        let register2 = self.registers[self.next_8_bits() as usize];
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
 
         if register1 > register2 {
             self.comparison_flag = true;
         } else {
             self.comparison_flag = false;
         }
+        Ok(())
     }
 
-    fn handle_lte(&mut self) -> () {
+    fn handle_lte(&mut self) -> Result<(), VmError> {
         /* LTE reg0 reg1 */
-        let register1 = self.registers[self.next_8_bits() as usize];
-        let register2 = self.registers[self.next_8_bits() as usize];
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
 
         if register1 <= register2 {
             self.comparison_flag = true;
         } else {
             self.comparison_flag = false;
         }
+        Ok(())
     }
 
-    fn handle_lt(&mut self) -> () {
+    fn handle_lt(&mut self) -> Result<(), VmError> {
         /* LT reg0 reg1 */
-        let register1 = self.registers[self.next_8_bits() as usize];
-        let register2 = self.registers[self.next_8_bits() as usize];
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
 
         if register1 < register2 {
             self.comparison_flag = true;
         } else {
             self.comparison_flag = false;
         }
+        Ok(())
     }
 
-    fn handle_eq(&mut self) -> () {
+    fn handle_eq(&mut self) -> Result<(), VmError> {
         /* EQ reg0 reg1 */
-        let register1 = self.registers[self.next_8_bits() as usize];
-        let register2 = self.registers[self.next_8_bits() as usize];
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
 
         if register1 == register2 {
             self.comparison_flag = true;
         } else {
             self.comparison_flag = false;
         }
+        Ok(())
     }
 
-    fn handle_jmpb(&mut self) -> () {
+    fn handle_jmpb(&mut self) -> Result<(), VmError> {
         /* JMPB regTarget */
-        let step = self.registers[self.next_8_bits() as usize];
-        self.pc -= step as usize;
+        let step = self.registers[self.next_register()?];
+        self.pc = self.jump_target(self.pc as i64 - step as i64)?;
+        Ok(())
     }
 
-    fn handle_jmpf(&mut self) -> () {
+    fn handle_jmpf(&mut self) -> Result<(), VmError> {
         /* JMPF regTarget */
-        let step = self.registers[self.next_8_bits() as usize];
-        self.pc += step as usize;
+        let step = self.registers[self.next_register()?];
+        self.pc = self.jump_target(self.pc as i64 + step as i64)?;
+        Ok(())
     }
 
-    fn handle_jmp(&mut self) -> () {
+    fn handle_jmp(&mut self) -> Result<(), VmError> {
         /* JMP regTarget */
-        let target = self.registers[self.next_8_bits() as usize];
-        self.pc = target as usize;
+        let target = self.registers[self.next_register()?];
+        self.pc = self.jump_target(target as i64)?;
+        Ok(())
     }
 
-    fn handle_div(&mut self) -> () {
+    fn handle_div(&mut self) -> Result<(), VmError> {
         /* DIV reg1 reg2 regTarget */
-        let register1 = self.registers[self.next_8_bits() as usize];
-        let register2 = self.registers[self.next_8_bits() as usize];
-        self.registers[self.next_8_bits() as usize] = (register1 / register2) as i32;
-        self.remainder = (register1 % register2) as u32;
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        if register2 == 0 {
+            return Err(DivideByZero {
+                pc: self.instruction_pc,
+            });
+        }
+        self.registers[self.next_register()?] = register1.wrapping_div(register2);
+        self.remainder = register1.wrapping_rem(register2);
+        Ok(())
     }
 
-    fn handle_mul(&mut self) -> () {
+    fn handle_mul(&mut self) -> Result<(), VmError> {
         /* MUL reg1 reg2 regTarget */
-        let register1 = self.registers[self.next_8_bits() as usize];
-        let register2 = self.registers[self.next_8_bits() as usize];
-        self.registers[self.next_8_bits() as usize] = (register1 * register2) as i32;
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        let result = self.overflow(
+            register1.checked_mul(register2),
+            register1.wrapping_mul(register2),
+            register1.saturating_mul(register2),
+        )?;
+        self.registers[self.next_register()?] = result;
+        Ok(())
     }
 
-    fn handle_sub(&mut self) -> () {
+    fn handle_sub(&mut self) -> Result<(), VmError> {
         /* SUB reg1 reg2 regTarget */
-        let register1 = self.registers[self.next_8_bits() as usize];
-        let register2 = self.registers[self.next_8_bits() as usize];
-        self.registers[self.next_8_bits() as usize] = (register1 - register2) as i32;
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        let result = self.overflow(
+            register1.checked_sub(register2),
+            register1.wrapping_sub(register2),
+            register1.saturating_sub(register2),
+        )?;
+        self.registers[self.next_register()?] = result;
+        Ok(())
     }
 
-    fn handle_add(&mut self) -> () {
+    fn handle_add(&mut self) -> Result<(), VmError> {
         /* ADD reg1 reg2 regTarget */
-        let register1 = self.registers[self.next_8_bits() as usize];
-        let register2 = self.registers[self.next_8_bits() as usize];
-        self.registers[self.next_8_bits() as usize] = register1 + register2;
+        let register1 = self.registers[self.next_register()?];
+        let register2 = self.registers[self.next_register()?];
+        let result = self.overflow(
+            register1.checked_add(register2),
+            register1.wrapping_add(register2),
+            register1.saturating_add(register2),
+        )?;
+        self.registers[self.next_register()?] = result;
+        Ok(())
     }
 
-    fn handle_load(&mut self) -> () {
+    fn handle_load32(&mut self) -> Result<(), VmError> {
+        /* LOAD32 reg number[31..24] number[23..16] number[15..8] number[7..0] */
+        let register = self.next_register()?;
+        let number = self.next_32_bits()?;
+        self.registers[register] = number as i32;
+        Ok(())
+    }
+
+    fn handle_load(&mut self) -> Result<(), VmError> {
         /* LOAD reg numberH numberL*/
-        let register = self.next_8_bits() as usize;
-        let number = self.next_16_bits() as u16;
+        let register = self.next_register()?;
+        let number = self.next_16_bits()?;
         self.registers[register] = number as i32;
+        Ok(())
+    }
+
+    pub fn run(&mut self) -> Result<VmState, VmError> {
+        loop {
+            let state = self.step()?;
+            if state != Running {
+                return Ok(state);
+            }
+        }
     }
 
-    pub fn run(&mut self) {
-        let mut terminated = false;
-        while !terminated {
-            terminated = self.execute_instruction().unwrap();
+    /* run at most `steps` instructions, Running is returned when the steps are used up */
+    pub fn run_for(&mut self, steps: u64) -> Result<VmState, VmError> {
+        let mut state = self.state;
+        for _ in 0..steps {
+            state = self.step()?;
+            if state != Running {
+                return Ok(state);
+            }
         }
+        return Ok(state);
     }
 
-    fn verify_header(&mut self) -> bool {
-        if self.program[0..4] != ELF_HEADER_PREFIX.to_owned() {
-            return false;
+    /* run until pc reaches the breakpoint, at least one instruction is executed */
+    pub fn run_until(&mut self, breakpoint: usize) -> Result<VmState, VmError> {
+        loop {
+            let state = self.step()?;
+            if state != Running || self.pc == breakpoint {
+                return Ok(state);
+            }
         }
-        true
     }
 
-    pub fn load_program(&mut self, program: Vec<u8>) {
-        self.program = program;
-        if !self.verify_header() {
-            println!("Not ELF file.")
+    /*
+     * load a DELF file, code and ro_data come from its verified sections and pc starts at the
+     * entry. The labels of a debug section replace the ones given to set_labels.
+     */
+    pub fn load_program(&mut self, program: Vec<u8>) -> Result<(), DELFError> {
+        let header = DELFHeader::decode_from_bytes(&program)?;
+        header.check_sections(program.len())?;
+
+        let code_start = header.code_offset as usize;
+        let code_end = code_start + header.code_length as usize;
+        let ro_data_start = header.ro_data_offset as usize;
+        let ro_data_end = ro_data_start + header.ro_data_length as usize;
+        let code = &program[code_start..code_end];
+        let ro_data = &program[ro_data_start..ro_data_end];
+        verify(code, ro_data).map_err(|error| Rejected { error })?;
+        if !verify_entry(code, header.entry as usize) {
+            return Err(EntryOutOfBounds {
+                entry: header.entry,
+                code_length: header.code_length,
+            });
         }
-        let pro: Vec<u8> = self.program[64..].to_owned();
-        self.program = pro;
+        let debug_info = match header.debug_length {
+            0 => None,
+            length => {
+                let start = header.debug_offset as usize;
+                Some(DebugInfo::decode_from_bytes(
+                    &program[start..start + length as usize],
+                )?)
+            }
+        };
+
+        self.program = code.to_vec();
+        self.ro_data = ro_data.to_vec();
+        if let Some(ref debug_info) = debug_info {
+            self.labels = debug_info.code_labels.clone();
+        }
+        self.debug_info = debug_info;
+        self.entry = header.entry as usize;
+        self.pc = self.entry;
+        self.state = Running;
+        self.exit_code = None;
+        self.instruction_boundaries.clear();
+        Ok(())
+    }
+
+    /* the loaded program, ro_data and debug section as a DELF file, load_program reads it back */
+    pub fn to_delf(&self) -> Vec<u8> {
+        let header = DELFHeader::for_sections(
+            self.entry as u32,
+            self.program.len() as u32,
+            self.ro_data.len() as u32,
+        );
+        let debug_section = self
+            .debug_info
+            .as_ref()
+            .map_or(Vec::new(), |debug_info| debug_info.encode_to_bytes());
+        let mut file = if debug_section.is_empty() {
+            header.encode_to_bytes()
+        } else {
+            header
+                .with_debug_section(debug_section.len() as u32)
+                .encode_to_bytes()
+        };
+        file.extend_from_slice(&self.program);
+        file.extend_from_slice(&self.ro_data);
+        file.extend_from_slice(&debug_section);
+        return file;
     }
 
     pub fn set_ro_data(&mut self, ro_section: Vec<u8>) {
@@ -0,0 +1,190 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+use crate::vm::vm_error::VmError::{
+    BadRoDataOffset, DivideByZero, HeapOutOfBounds, IllegalOpcode, IntegerOverflow,
+    InvalidAllocationSize, InvalidCallTarget, InvalidFree, InvalidRegister, OutputFailed,
+    PcOutOfBounds, StackOverflow, StackSlotOutOfBounds, StackUnderflow, SyscallFailed,
+    UnknownSyscall,
+};
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/* every fault carries the pc of the instruction that raised it */
+#[derive(Debug, Clone, PartialEq)]
+pub enum VmError {
+    StackUnderflow {
+        depth: usize,
+        pc: usize,
+    },
+    StackSlotOutOfBounds {
+        offset: i32,
+        pc: usize,
+    },
+    StackOverflow {
+        depth: usize,
+        pc: usize,
+    },
+    DivideByZero {
+        pc: usize,
+    },
+    IntegerOverflow {
+        pc: usize,
+    },
+    InvalidRegister {
+        register: u8,
+        pc: usize,
+    },
+    PcOutOfBounds {
+        address: i64,
+        pc: usize,
+    },
+    InvalidCallTarget {
+        address: i64,
+        pc: usize,
+    },
+    BadRoDataOffset {
+        offset: i32,
+        pc: usize,
+    },
+    HeapOutOfBounds {
+        address: i64,
+        pc: usize,
+    },
+    InvalidAllocationSize {
+        size: i32,
+        pc: usize,
+    },
+    InvalidFree {
+        address: i32,
+        pc: usize,
+    },
+    IllegalOpcode {
+        opcode: u8,
+        pc: usize,
+    },
+    UnknownSyscall {
+        number: u16,
+        pc: usize,
+    },
+    SyscallFailed {
+        number: u16,
+        message: String,
+        pc: usize,
+    },
+    OutputFailed {
+        message: String,
+        pc: usize,
+    },
+}
+
+impl VmError {
+    pub fn pc(&self) -> usize {
+        match *self {
+            StackUnderflow { pc, .. }
+            | StackOverflow { pc, .. }
+            | StackSlotOutOfBounds { pc, .. }
+            | DivideByZero { pc }
+            | IntegerOverflow { pc }
+            | InvalidRegister { pc, .. }
+            | PcOutOfBounds { pc, .. }
+            | InvalidCallTarget { pc, .. }
+            | BadRoDataOffset { pc, .. }
+            | HeapOutOfBounds { pc, .. }
+            | InvalidAllocationSize { pc, .. }
+            | InvalidFree { pc, .. }
+            | IllegalOpcode { pc, .. }
+            | UnknownSyscall { pc, .. }
+            | SyscallFailed { pc, .. }
+            | OutputFailed { pc, .. } => pc,
+        }
+    }
+}
+
+impl Error for VmError {
+    fn description(&self) -> &str {
+        match self {
+            _ => "VM Error:",
+        }
+    }
+}
+
+impl Display for VmError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            StackUnderflow { ref depth, ref pc } => f.write_str(&format!(
+                "Stack underflow at call depth {}, at pc: {}",
+                depth, pc
+            )),
+            StackOverflow { ref depth, ref pc } => f.write_str(&format!(
+                "Stack overflow at call depth {}, at pc: {}",
+                depth, pc
+            )),
+            StackSlotOutOfBounds { ref offset, ref pc } => f.write_str(&format!(
+                "Stack slot bp{:+} out of bounds, at pc: {}",
+                offset, pc
+            )),
+            DivideByZero { ref pc } => f.write_str(&format!("Divide by zero, at pc: {}", pc)),
+            IntegerOverflow { ref pc } => f.write_str(&format!("Integer overflow, at pc: {}", pc)),
+            InvalidRegister {
+                ref register,
+                ref pc,
+            } => f.write_str(&format!("Invalid register ${}, at pc: {}", register, pc)),
+            PcOutOfBounds {
+                ref address,
+                ref pc,
+            } => f.write_str(&format!(
+                "Program address {} out of bounds, at pc: {}",
+                address, pc
+            )),
+            InvalidCallTarget {
+                ref address,
+                ref pc,
+            } => f.write_str(&format!(
+                "Call target {} is not an instruction, at pc: {}",
+                address, pc
+            )),
+            BadRoDataOffset { ref offset, ref pc } => f.write_str(&format!(
+                "Bad read-only data offset {}, at pc: {}",
+                offset, pc
+            )),
+            HeapOutOfBounds {
+                ref address,
+                ref pc,
+            } => f.write_str(&format!(
+                "Heap address {} out of bounds, at pc: {}",
+                address, pc
+            )),
+            InvalidAllocationSize { ref size, ref pc } => {
+                f.write_str(&format!("Invalid allocation size {}, at pc: {}", size, pc))
+            }
+            InvalidFree {
+                ref address,
+                ref pc,
+            } => f.write_str(&format!(
+                "Free of unallocated heap address {}, at pc: {}",
+                address, pc
+            )),
+            IllegalOpcode { ref opcode, ref pc } => {
+                f.write_str(&format!("Illegal opcode {}, at pc: {}", opcode, pc))
+            }
+            UnknownSyscall { ref number, ref pc } => {
+                f.write_str(&format!("Unknown syscall {}, at pc: {}", number, pc))
+            }
+            SyscallFailed {
+                ref number,
+                ref message,
+                ref pc,
+            } => f.write_str(&format!(
+                "Syscall {} failed: {}, at pc: {}",
+                number, message, pc
+            )),
+            OutputFailed {
+                ref message,
+                ref pc,
+            } => f.write_str(&format!("Output failed: {}, at pc: {}", message, pc)),
+        }
+    }
+}
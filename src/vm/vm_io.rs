@@ -0,0 +1,68 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::io;
+use std::io::{stdin, stdout, BufRead, BufReader, Write};
+use std::rc::Rc;
+
+/* where program output goes and where program input comes from, stdout/stdin by default */
+pub struct VmIo {
+    pub(crate) output: Box<dyn Write>,
+    pub(crate) input: Box<dyn BufRead>,
+}
+
+impl VmIo {
+    pub fn stdio() -> VmIo {
+        VmIo {
+            output: Box::new(stdout()),
+            input: Box::new(BufReader::new(stdin())),
+        }
+    }
+}
+
+impl Debug for VmIo {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("VmIo")
+    }
+}
+
+/* a Write sink that can still be read after it is handed to the VM */
+#[derive(Debug, Clone, Default)]
+pub struct SharedBuffer {
+    bytes: Rc<RefCell<Vec<u8>>>,
+}
+
+impl SharedBuffer {
+    pub fn new() -> SharedBuffer {
+        SharedBuffer {
+            bytes: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    pub fn contents(&self) -> Vec<u8> {
+        return self.bytes.borrow().clone();
+    }
+
+    pub fn text(&self) -> String {
+        return String::from_utf8_lossy(&self.bytes.borrow()).to_string();
+    }
+
+    pub fn clear(&self) {
+        self.bytes.borrow_mut().clear();
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
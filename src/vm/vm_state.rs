@@ -0,0 +1,15 @@
+/*
+ * Copyright (c) 2019. NeroYang
+ */
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum VmState {
+    /* stopped on a step limit or breakpoint, more instructions can be run */
+    Running,
+    /* HLT executed or pc ran off the end of program, carries the exit status */
+    Halted(i32),
+    /* the last instruction raised a VmError */
+    Faulted,
+    /* the instruction budget is used up */
+    BudgetExhausted,
+}